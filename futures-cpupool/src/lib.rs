@@ -42,12 +42,15 @@ extern crate crossbeam;
 extern crate futures;
 extern crate num_cpus;
 
+use std::cell::RefCell;
+use std::mem;
 use std::panic::{self, AssertUnwindSafe};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
 use std::thread;
 
 use crossbeam::sync::MsQueue;
+use crossbeam::sync::chase_lev::{self, Steal, Stealer};
 use futures::{IntoFuture, Future, Poll, Async};
 use futures::future::lazy;
 use futures::sync::oneshot::{channel, Sender, Receiver};
@@ -67,6 +70,10 @@ use futures::executor::{self, Run, Executor};
 /// Currently `CpuPool` implements `Clone` which just clones a new reference to
 /// the underlying thread pool.
 ///
+/// Each worker thread keeps its own queue of tasks, and idle workers steal
+/// work from busy ones. A task unparked from one of the pool's own threads is
+/// typically run next on that same thread.
+///
 /// **Note:** if you use CpuPool inside a library it's better accept a
 /// `Builder` object for thread configuration rather than configuring just
 /// pool size.  This not only future proof for other settings but also allows
@@ -99,14 +106,65 @@ fn _assert() {
     _assert_sync::<CpuPool>();
 }
 
+// Tasks are scheduled onto a pool in one of three places:
+//
+// * Each worker thread has a "LIFO slot" holding the task most recently
+//   unparked from that same thread. It's run next by that worker, which keeps
+//   a task that was just made ready by another (for example by sending it a
+//   message) hot in the cache. Idle workers can still steal from this slot,
+//   as the task running on its worker may go on to block waiting for it.
+// * Each worker thread also has a local work-stealing deque. Tasks displaced
+//   from the LIFO slot are pushed here, and idle workers steal from the other
+//   end of it.
+// * Tasks spawned or unparked from threads which aren't workers of this pool
+//   go onto the shared `queue`, which all workers pull from.
+//
+// Workers with nothing to do go to sleep on `sleep_cvar`, and anything which
+// makes new work visible to other workers wakes one of them up.
+//
+// Note that the shared queue stores `Option<Run>` rather than `Run` as
+// `MsQueue` leaves its sentinel node uninitialized, which is not allowed for
+// types like `Run` that can't be null. It only ever contains `Some`.
 struct Inner {
-    queue: MsQueue<Message>,
+    queue: MsQueue<Option<Run>>,
+    stealers: Vec<Stealer<Run>>,
+    // The LIFO slot of each worker, indexed like `stealers`
+    lifo_slots: Vec<Mutex<Option<Run>>>,
+    sleepers: AtomicUsize,
+    sleep_lock: Mutex<()>,
+    sleep_cvar: Condvar,
+    shutdown: AtomicBool,
     cnt: AtomicUsize,
-    size: usize,
     after_start: Option<Arc<Fn() + Send + Sync>>,
     before_stop: Option<Arc<Fn() + Send + Sync>>,
 }
 
+// State owned by each worker thread, reachable through `WORKER` so that
+// `Executor::execute` can schedule tasks locally when called on a worker.
+struct Worker {
+    // Address of the `Inner` for the pool this thread is working for
+    pool: usize,
+    // Index of this worker's LIFO slot and stealer in `Inner`
+    index: usize,
+    // Number of tasks taken from `lifo` in a row, see `MAX_LIFO_STREAK`
+    lifo_streak: usize,
+    deque: chase_lev::Worker<Run>,
+    // Set by `CpuPool::spawn` for the duration of scheduling a new task
+    spawning: bool,
+}
+
+thread_local!(static WORKER: RefCell<Option<Worker>> = RefCell::new(None));
+
+// Maximum number of times in a row a worker will run the task in its LIFO
+// slot. Two tasks which keep unparking each other would otherwise starve
+// everything else on the worker's deque.
+const MAX_LIFO_STREAK: usize = 16;
+
+// How often, in number of tasks run, a worker checks the shared queue before
+// its own local tasks. Without this tasks submitted from outside the pool could
+// be starved by a busy worker.
+const SHARED_QUEUE_INTERVAL: usize = 61;
+
 /// The type of future returned from the `CpuPool::spawn` function, which
 /// proxies the futures running on the thread pool.
 ///
@@ -118,11 +176,6 @@ pub struct CpuFuture<T, E> {
     keep_running_flag: Arc<AtomicBool>,
 }
 
-enum Message {
-    Run(Run),
-    Close,
-}
-
 impl CpuPool {
     /// Creates a new thread pool with `size` worker threads associated with it.
     ///
@@ -184,6 +237,16 @@ impl CpuPool {
             tx: Some(tx),
             keep_running_flag: keep_running_flag.clone(),
         };
+
+        // Tasks spawned from one of our workers go on that worker's deque
+        // rather than in its LIFO slot, so another worker can pick them up
+        // even if the spawning task goes on to block waiting for them.
+        let pool = &*self.inner as *const Inner as usize;
+        WORKER.with(|w| {
+            if let Some(ref mut worker) = *w.borrow_mut() {
+                worker.spawning = worker.pool == pool;
+            }
+        });
         executor::spawn(sender).execute(self.inner.clone());
         CpuFuture { inner: rx , keep_running_flag: keep_running_flag.clone() }
     }
@@ -208,17 +271,156 @@ impl CpuPool {
     }
 }
 
-fn work(inner: &Inner) {
+fn work(inner: &Inner, index: usize, deque: chase_lev::Worker<Run>) {
     inner.after_start.as_ref().map(|fun| fun());
+    WORKER.with(|w| {
+        *w.borrow_mut() = Some(Worker {
+            pool: inner as *const Inner as usize,
+            index: index,
+            lifo_streak: 0,
+            deque: deque,
+            spawning: false,
+        });
+    });
+    let mut tick = 0;
     loop {
-        match inner.queue.pop() {
-            Message::Run(r) => r.run(),
-            Message::Close => break,
-        }
+        tick += 1;
+        let run = match inner.next_run(index, tick) {
+            Some(r) => r,
+            None if inner.shutdown.load(Ordering::SeqCst) => break,
+            None => match inner.sleep(index) {
+                Some(r) => r,
+                None => continue,
+            },
+        };
+        run.run();
     }
+    WORKER.with(|w| *w.borrow_mut() = None);
     inner.before_stop.as_ref().map(|fun| fun());
 }
 
+impl Inner {
+    // Finds the next task for the worker at `index` to run, if any.
+    fn next_run(&self, index: usize, tick: usize) -> Option<Run> {
+        if tick % SHARED_QUEUE_INTERVAL == 0 {
+            if let Some(r) = self.pop_shared() {
+                return Some(r)
+            }
+        }
+        let local = WORKER.with(|w| w.borrow_mut().as_mut().unwrap().pop(self));
+        if let Some(r) = local {
+            return Some(r)
+        }
+        if let Some(r) = self.pop_shared() {
+            return Some(r)
+        }
+        self.steal(index)
+    }
+
+    fn push_shared(&self, run: Run) {
+        self.queue.push(Some(run));
+    }
+
+    fn pop_shared(&self) -> Option<Run> {
+        self.queue.try_pop().and_then(|r| r)
+    }
+
+    // Attempts to steal a task from one of the other workers, starting with
+    // the one after `index`. Their deques are tried before their LIFO slots,
+    // which are left to their own worker if possible.
+    fn steal(&self, index: usize) -> Option<Run> {
+        let len = self.stealers.len();
+        for i in 1..len {
+            let stealer = &self.stealers[(index + i) % len];
+            loop {
+                match stealer.steal() {
+                    Steal::Data(r) => return Some(r),
+                    Steal::Empty => break,
+                    Steal::Abort => {}
+                }
+            }
+        }
+        for i in 1..len {
+            let slot = self.lifo_slots[(index + i) % len].lock().unwrap().take();
+            if slot.is_some() {
+                return slot
+            }
+        }
+        None
+    }
+
+    // Blocks the worker at `index` until more work may be available or the
+    // pool is shut down. If work shows up while getting ready to sleep then
+    // that task is returned instead.
+    fn sleep(&self, index: usize) -> Option<Run> {
+        let lock = self.sleep_lock.lock().unwrap();
+
+        // Announce that we're about to sleep *before* checking for work one
+        // last time. Anyone making work available checks `sleepers` after
+        // doing so, so either we see their work here or they see us and wait
+        // on `sleep_lock` to wake us up.
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        atomic::fence(Ordering::SeqCst);
+
+        let run = if self.shutdown.load(Ordering::SeqCst) {
+            None
+        } else {
+            match self.pop_shared() {
+                Some(r) => Some(r),
+                None => self.steal(index),
+            }
+        };
+        if run.is_none() && !self.shutdown.load(Ordering::SeqCst) {
+            drop(self.sleep_cvar.wait(lock).unwrap());
+        }
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        run
+    }
+
+    // Wakes up a sleeping worker, if any, after work has been made available
+    // to them.
+    fn notify_sleeper(&self) {
+        atomic::fence(Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            let _lock = self.sleep_lock.lock().unwrap();
+            self.sleep_cvar.notify_one();
+        }
+    }
+}
+
+impl Worker {
+    // Schedules `run` on this worker.
+    //
+    // Newly spawned tasks go straight to the deque, while unparked tasks go in
+    // the LIFO slot and bump whatever was there before onto the deque.
+    fn push(&mut self, inner: &Inner, run: Run) {
+        if mem::replace(&mut self.spawning, false) {
+            self.deque.push(run);
+            return
+        }
+        let prev = inner.lifo_slots[self.index].lock().unwrap().replace(run);
+        if let Some(prev) = prev {
+            self.deque.push(prev);
+        }
+    }
+
+    fn pop(&mut self, inner: &Inner) -> Option<Run> {
+        let lifo = inner.lifo_slots[self.index].lock().unwrap().take();
+        if let Some(r) = lifo {
+            if self.lifo_streak < MAX_LIFO_STREAK {
+                self.lifo_streak += 1;
+                return Some(r)
+            }
+            // This slot has had its fair share of turns, so send its task to
+            // the back of the shared queue and move on to other work.
+            inner.push_shared(r);
+            inner.notify_sleeper();
+        }
+        self.lifo_streak = 0;
+        self.deque.try_pop()
+    }
+}
+
 impl Clone for CpuPool {
     fn clone(&self) -> CpuPool {
         self.inner.cnt.fetch_add(1, Ordering::Relaxed);
@@ -229,16 +431,43 @@ impl Clone for CpuPool {
 impl Drop for CpuPool {
     fn drop(&mut self) {
         if self.inner.cnt.fetch_sub(1, Ordering::Relaxed) == 1 {
-            for _ in 0..self.inner.size {
-                self.inner.queue.push(Message::Close);
-            }
+            // Workers exit once they run out of work after seeing this flag,
+            // so wake up everyone who's currently asleep.
+            self.inner.shutdown.store(true, Ordering::SeqCst);
+            let _lock = self.inner.sleep_lock.lock().unwrap();
+            self.inner.sleep_cvar.notify_all();
         }
     }
 }
 
+// Lets a pool be used wherever an `Arc<Executor>` is expected, such as with
+// `executor::spawn_with_handle` or a `TaskGroup`.
+impl Executor for CpuPool {
+    fn execute(&self, run: Run) {
+        self.inner.execute(run)
+    }
+}
+
 impl Executor for Inner {
     fn execute(&self, run: Run) {
-        self.queue.push(Message::Run(run))
+        // If we're running on one of our own workers then keep the task local
+        // to that worker, otherwise it goes on the shared queue.
+        let pool = self as *const Inner as usize;
+        let local = WORKER.with(|w| {
+            match *w.borrow_mut() {
+                Some(ref mut worker) if worker.pool == pool => {
+                    worker.push(self, run);
+                    None
+                }
+                _ => Some(run),
+            }
+        });
+        if let Some(run) = local {
+            self.push_shared(run);
+        }
+        // Even a task in our LIFO slot needs someone else to run it if the
+        // current task goes on to block this worker waiting for it.
+        self.notify_sleeper();
     }
 }
 
@@ -341,24 +570,33 @@ impl Builder {
 
     /// Create CpuPool with configured parameters
     pub fn create(&mut self) -> CpuPool {
+        assert!(self.pool_size > 0);
+
+        let (workers, stealers): (Vec<_>, Vec<_>) = (0..self.pool_size)
+            .map(|_| chase_lev::deque())
+            .unzip();
         let pool = CpuPool {
             inner: Arc::new(Inner {
                 queue: MsQueue::new(),
+                stealers: stealers,
+                lifo_slots: (0..self.pool_size).map(|_| Mutex::new(None)).collect(),
+                sleepers: AtomicUsize::new(0),
+                sleep_lock: Mutex::new(()),
+                sleep_cvar: Condvar::new(),
+                shutdown: AtomicBool::new(false),
                 cnt: AtomicUsize::new(1),
-                size: self.pool_size,
                 after_start: self.after_start.clone(),
                 before_stop: self.before_stop.clone(),
             }),
         };
-        assert!(self.pool_size > 0);
 
-        for counter in 0..self.pool_size {
+        for (counter, deque) in workers.into_iter().enumerate() {
             let inner = pool.inner.clone();
             let mut thread_builder = thread::Builder::new();
             if let Some(ref name_prefix) = self.name_prefix {
                 thread_builder = thread_builder.name(format!("{}{}", name_prefix, counter));
             }
-            thread_builder.spawn(move || work(&inner)).unwrap();
+            thread_builder.spawn(move || work(&inner, counter, deque)).unwrap();
         }
 
         return pool
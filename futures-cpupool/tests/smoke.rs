@@ -1,11 +1,16 @@
 extern crate futures;
 extern crate futures_cpupool;
 
+use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::mpsc as std_mpsc;
 use std::thread;
 use std::time::Duration;
 
+use futures::{Sink, Stream};
 use futures::future::{Future, BoxFuture};
+use futures::stream;
+use futures::sync::mpsc;
 use futures_cpupool::{CpuPool, Builder};
 
 fn done<T: Send + 'static>(t: T) -> BoxFuture<T, ()> {
@@ -108,3 +113,95 @@ fn thread_name() {
     });
     let _ = future.wait();
 }
+
+#[test]
+fn spawn_from_worker_and_block() {
+    let pool = CpuPool::new(2);
+    let pool2 = pool.clone();
+    let f = pool.spawn_fn(move || {
+        // The child can't run on this worker while we're blocked, so this
+        // relies on it being picked up by the other one.
+        pool2.spawn(done(1)).wait()
+    });
+    assert_eq!(f.wait().unwrap(), 1);
+}
+
+#[test]
+fn fan_out() {
+    const N: usize = 1000;
+    let pool = CpuPool::new(4);
+    let pool2 = pool.clone();
+    let f = pool.spawn_fn(move || {
+        let children = (0..N).map(|i| pool2.spawn(done(i)));
+        futures::future::join_all(children.collect::<Vec<_>>())
+    });
+    let res = f.wait().unwrap();
+    assert_eq!(res, (0..N).collect::<Vec<_>>());
+}
+
+#[test]
+fn ping_pong_between_tasks() {
+    const N: usize = 1000;
+
+    #[derive(Debug)]
+    struct Closed;
+
+    impl<T> From<mpsc::SendError<T>> for Closed {
+        fn from(_: mpsc::SendError<T>) -> Closed {
+            Closed
+        }
+    }
+
+    let pool = CpuPool::new(2);
+    let (tx1, rx1) = mpsc::channel::<usize>(0);
+    let (tx2, rx2) = mpsc::channel::<usize>(0);
+
+    // `a` echoes everything it receives back incremented by one, while `b`
+    // feeds it numbers and collects the replies.
+    let a = pool.spawn(rx2.map(|i| i + 1)
+                          .map_err(|()| Closed)
+                          .forward(tx1)
+                          .map(|_| ()));
+    let b = pool.spawn(tx2.send_all(stream::iter((0..N).map(Ok)))
+                          .map(|_| ())
+                          .map_err(Closed::from)
+                          .join(rx1.collect().map_err(|()| Closed))
+                          .map(|(_, replies)| replies));
+
+    a.wait().unwrap();
+    assert_eq!(b.wait().unwrap(), (1..N + 1).collect::<Vec<_>>());
+}
+
+#[test]
+fn task_group() {
+    let pool = CpuPool::new(2);
+    let mut group = futures::executor::TaskGroup::new(Arc::new(pool.clone()));
+    for i in 0..8 {
+        group.spawn(done(i));
+    }
+    assert_eq!(group.wait().unwrap(), (0..8).collect::<Vec<_>>());
+}
+
+#[test]
+fn block_on_unparked_task() {
+    // A task which unparks another one from a worker and then blocks that
+    // worker waiting on it must not stop the other task from running.
+    let pool = CpuPool::new(2);
+    let (tx, rx) = futures::sync::oneshot::channel::<u32>();
+    let (started_tx, started_rx) = std_mpsc::channel();
+    let b = pool.spawn(futures::future::lazy(move || {
+        started_tx.send(()).unwrap();
+        rx.map(|n| n + 1)
+    }));
+    started_rx.recv().unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    let a = pool.spawn(futures::future::lazy(move || {
+        tx.complete(6);
+        b.wait()
+    }));
+    let (res_tx, res_rx) = std_mpsc::channel();
+    thread::spawn(move || res_tx.send(a.wait()).unwrap());
+    let res = res_rx.recv_timeout(Duration::from_secs(5)).expect("pool deadlocked");
+    assert_eq!(res, Ok(7));
+}
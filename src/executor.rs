@@ -4,4 +4,325 @@
 //! which is needed when building *executors* (places where futures can run).
 // TODO: more dox
 
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::Arc;
+
+use {Future, Poll, Async};
+use future::{Abortable, AbortHandle, AbortError};
+use sync::oneshot;
+
 pub use task_impl::{Spawn, spawn, Unpark, Executor, Run};
+pub use task_impl::{LocalPool, LocalSpawner};
+
+/// Spawns a future onto the given executor, returning a future which resolves
+/// to the result of the spawned future.
+///
+/// This is similar to `spawn(f).execute(exec)` except that the future `f` is
+/// not required to resolve to `()`, and its result is instead delivered through
+/// the returned `SpawnHandle`. This works with any implementation of the
+/// `Executor` trait.
+///
+/// The spawned future keeps running even if the returned handle is dropped.
+/// If the executor drops the future before it completes, for example by
+/// dropping a `Run` without running it, then the handle resolves to
+/// `SpawnError::Canceled`.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use futures::Future;
+/// use futures::executor::{self, Executor, Run};
+/// use futures::future;
+///
+/// struct ThreadPerRun;
+///
+/// impl Executor for ThreadPerRun {
+///     fn execute(&self, r: Run) {
+///         thread::spawn(move || r.run());
+///     }
+/// }
+///
+/// let handle = executor::spawn_with_handle(future::ok::<u32, ()>(1),
+///                                          Arc::new(ThreadPerRun));
+/// assert_eq!(handle.wait().unwrap(), 1);
+/// ```
+pub fn spawn_with_handle<F>(f: F, exec: Arc<Executor>) -> SpawnHandle<F::Item, F::Error>
+    where F: Future + Send + 'static,
+          F::Item: Send + 'static,
+          F::Error: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    spawn(Execute { future: f, tx: Some(tx) }).execute(exec);
+    SpawnHandle { rx: rx }
+}
+
+/// A future representing the result of a future spawned onto an executor.
+///
+/// This is created by the `spawn_with_handle` function.
+#[must_use = "futures do nothing unless polled"]
+pub struct SpawnHandle<T, E> {
+    rx: oneshot::Receiver<Result<T, E>>,
+}
+
+impl<T, E> Future for SpawnHandle<T, E> {
+    type Item = T;
+    type Error = SpawnError<E>;
+
+    fn poll(&mut self) -> Poll<T, SpawnError<E>> {
+        match self.rx.poll() {
+            Ok(Async::Ready(Ok(t))) => Ok(Async::Ready(t)),
+            Ok(Async::Ready(Err(e))) => Err(SpawnError::Inner(e)),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(oneshot::Canceled) => Err(SpawnError::Canceled),
+        }
+    }
+}
+
+/// Error returned from a `SpawnHandle`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpawnError<E> {
+    /// The executor dropped the spawned future before it completed.
+    Canceled,
+    /// The spawned future resolved to an error.
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for SpawnError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpawnError::Canceled => write!(fmt, "spawned future was canceled"),
+            SpawnError::Inner(ref e) => e.fmt(fmt),
+        }
+    }
+}
+
+impl<E: Error> Error for SpawnError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            SpawnError::Canceled => "spawned future was canceled",
+            SpawnError::Inner(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            SpawnError::Canceled => None,
+            SpawnError::Inner(ref e) => Some(e),
+        }
+    }
+}
+
+// The future actually spawned by `spawn_with_handle`, which sends the result
+// of `future` to the `SpawnHandle`. If this is dropped before completing then
+// so is `tx`, which the handle sees as cancellation.
+struct Execute<F: Future> {
+    future: F,
+    tx: Option<oneshot::Sender<Result<F::Item, F::Error>>>,
+}
+
+impl<F: Future> Future for Execute<F> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let res = match self.future.poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(e)) => Ok(e),
+            Err(e) => Err(e),
+        };
+        self.tx.take().unwrap().complete(res);
+        Ok(Async::Ready(()))
+    }
+}
+
+/// A group of futures spawned onto an executor, which is itself a future
+/// resolving once all of them have finished.
+///
+/// Futures spawned through `spawn` run on the group's executor just like ones
+/// spawned with `spawn_with_handle`, but they're tied to the lifetime of the
+/// group: when the group is dropped, every child which is still running is
+/// aborted and dropped by the executor the next time it's polled. This makes
+/// it impossible to accidentally leave background work running.
+///
+/// The group resolves to the results of its children, in the order in which
+/// they were spawned. What happens when a child fails is controlled by the
+/// `FailurePolicy` the group was created with.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::thread;
+/// use futures::Future;
+/// use futures::executor::{Executor, Run, TaskGroup};
+/// use futures::future;
+///
+/// struct ThreadPerRun;
+///
+/// impl Executor for ThreadPerRun {
+///     fn execute(&self, r: Run) {
+///         thread::spawn(move || r.run());
+///     }
+/// }
+///
+/// let mut group = TaskGroup::new(Arc::new(ThreadPerRun));
+/// for i in 0..4 {
+///     group.spawn(future::lazy(move || future::ok::<u32, ()>(i * 2)));
+/// }
+/// assert_eq!(group.wait().unwrap(), vec![0, 2, 4, 6]);
+/// ```
+#[must_use = "futures do nothing unless polled"]
+pub struct TaskGroup<T, E> {
+    exec: Arc<Executor>,
+    policy: FailurePolicy,
+    children: Vec<Child<T, E>>,
+    // The first failure, if we're waiting for the other children to finish
+    // before reporting it
+    error: Option<SpawnError<E>>,
+}
+
+/// What a `TaskGroup` does when one of its children fails.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FailurePolicy {
+    /// Abort the remaining children and resolve to the error right away.
+    ///
+    /// This is the policy used by `TaskGroup::new`.
+    CancelRemaining,
+    /// Let the remaining children run to completion, and then resolve to the
+    /// first error.
+    WaitForAll,
+}
+
+enum Child<T, E> {
+    Running(AbortHandle, SpawnHandle<T, AbortError<E>>),
+    // `None` if the child failed or was aborted
+    Done(Option<T>),
+}
+
+impl<T, E> TaskGroup<T, E>
+    where T: Send + 'static,
+          E: Send + 'static,
+{
+    /// Creates a new, empty group which spawns its children onto `exec`, and
+    /// aborts the remaining children as soon as one of them fails.
+    pub fn new(exec: Arc<Executor>) -> TaskGroup<T, E> {
+        TaskGroup::with_policy(exec, FailurePolicy::CancelRemaining)
+    }
+
+    /// Creates a new, empty group which spawns its children onto `exec`, and
+    /// handles failures according to `policy`.
+    pub fn with_policy(exec: Arc<Executor>, policy: FailurePolicy) -> TaskGroup<T, E> {
+        TaskGroup {
+            exec: exec,
+            policy: policy,
+            children: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Spawns `f` onto this group's executor as a child of the group.
+    ///
+    /// The child starts running right away, but the group doesn't notice
+    /// that it has finished until the group is next polled. Children should
+    /// be spawned before the group is polled to completion, as a group which
+    /// has resolved doesn't track any further children.
+    pub fn spawn<F>(&mut self, f: F)
+        where F: Future<Item = T, Error = E> + Send + 'static,
+    {
+        let (handle, reg) = AbortHandle::new_pair();
+        let spawned = spawn_with_handle(Abortable::new(f, reg), self.exec.clone());
+        self.children.push(Child::Running(handle, spawned));
+    }
+}
+
+impl<T, E> TaskGroup<T, E> {
+    fn abort_all(&mut self) {
+        for child in self.children.iter() {
+            if let Child::Running(ref handle, _) = *child {
+                handle.abort();
+            }
+        }
+    }
+}
+
+impl<T, E> Future for TaskGroup<T, E> {
+    type Item = Vec<T>;
+    type Error = SpawnError<E>;
+
+    fn poll(&mut self) -> Poll<Vec<T>, SpawnError<E>> {
+        let mut running = false;
+        for i in 0..self.children.len() {
+            let res = match self.children[i] {
+                Child::Running(_, ref mut handle) => {
+                    match handle.poll() {
+                        Ok(Async::NotReady) => {
+                            running = true;
+                            continue
+                        }
+                        Ok(Async::Ready(t)) => Ok(t),
+                        Err(e) => Err(e),
+                    }
+                }
+                Child::Done(_) => continue,
+            };
+            let error = match res {
+                Ok(t) => {
+                    self.children[i] = Child::Done(Some(t));
+                    continue
+                }
+                // Only we abort children, after which we don't care how they
+                // finish
+                Err(SpawnError::Inner(AbortError::Aborted)) => None,
+                Err(SpawnError::Inner(AbortError::Inner(e))) => Some(SpawnError::Inner(e)),
+                Err(SpawnError::Canceled) => Some(SpawnError::Canceled),
+            };
+            self.children[i] = Child::Done(None);
+            if let Some(e) = error {
+                if self.policy == FailurePolicy::CancelRemaining {
+                    self.abort_all();
+                    self.children.clear();
+                    return Err(e)
+                }
+                if self.error.is_none() {
+                    self.error = Some(e);
+                }
+            }
+        }
+
+        if running {
+            return Ok(Async::NotReady)
+        }
+        if let Some(e) = self.error.take() {
+            self.children.clear();
+            return Err(e)
+        }
+        let children = mem::replace(&mut self.children, Vec::new());
+        Ok(Async::Ready(children.into_iter().map(|c| {
+            match c {
+                Child::Done(Some(t)) => t,
+                _ => unreachable!(),
+            }
+        }).collect()))
+    }
+}
+
+impl<T, E> Drop for TaskGroup<T, E> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+impl<T, E> fmt::Debug for TaskGroup<T, E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TaskGroup")
+           .field("policy", &self.policy)
+           .field("children", &self.children.len())
+           .finish()
+    }
+}
@@ -0,0 +1,207 @@
+//! Definition of the `Abortable` combinator, a future or stream which can be
+//! remotely aborted through an `AbortHandle`.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+
+use {Future, Poll, Async};
+use stream::Stream;
+use task::{self, Task};
+
+/// A future or stream which can be remotely aborted through an `AbortHandle`.
+///
+/// This is created by the `future::abortable` and `stream::abortable`
+/// functions, or by pairing a value with an `AbortRegistration` through
+/// `Abortable::new`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Abortable<T> {
+    inner: T,
+    state: Arc<AbortState>,
+    // Set once a stream has reported `Aborted`, after which it terminates
+    done: bool,
+}
+
+/// A handle to an `Abortable` future or stream which can be used to abort it.
+///
+/// Handles can be cloned and sent to other threads, and all clones abort the
+/// same `Abortable`.
+#[derive(Clone)]
+pub struct AbortHandle {
+    state: Arc<AbortState>,
+}
+
+/// A registration which ties an `AbortHandle` to the `Abortable` it controls.
+///
+/// This is created alongside its handle by `AbortHandle::new_pair`, and is
+/// consumed by `Abortable::new`.
+pub struct AbortRegistration {
+    state: Arc<AbortState>,
+}
+
+struct AbortState {
+    aborted: AtomicBool,
+    task: Mutex<Option<Task>>,
+}
+
+/// Error returned from an `Abortable` future or stream.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AbortError<E> {
+    /// The future or stream was aborted through its `AbortHandle`.
+    Aborted,
+    /// The underlying future or stream resolved to an error.
+    Inner(E),
+}
+
+/// Creates a new `Abortable` future and a handle which can be used to abort
+/// it.
+///
+/// Once `abort` is called on the returned handle, the future will resolve to
+/// `AbortError::Aborted` the next time it's polled, and the task it's running
+/// on will be unparked so that happens promptly. The underlying future is no
+/// longer polled after it's been aborted.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::future::{self, AbortError};
+///
+/// let (future, handle) = future::abortable(future::empty::<(), ()>());
+/// handle.abort();
+/// assert_eq!(future.wait(), Err(AbortError::Aborted));
+/// ```
+pub fn abortable<F>(future: F) -> (Abortable<F>, AbortHandle)
+    where F: Future,
+{
+    let (handle, reg) = AbortHandle::new_pair();
+    (Abortable::new(future, reg), handle)
+}
+
+impl AbortHandle {
+    /// Creates a new `AbortHandle` along with the `AbortRegistration` which
+    /// can be used to construct the `Abortable` it controls.
+    pub fn new_pair() -> (AbortHandle, AbortRegistration) {
+        let state = Arc::new(AbortState {
+            aborted: AtomicBool::new(false),
+            task: Mutex::new(None),
+        });
+        (AbortHandle { state: state.clone() }, AbortRegistration { state: state })
+    }
+
+    /// Aborts the `Abortable` future or stream associated with this handle.
+    ///
+    /// If the task running it is currently blocked then it's unparked, and
+    /// the next time it's polled it'll return `AbortError::Aborted`. Calling
+    /// this more than once has no further effect.
+    pub fn abort(&self) {
+        self.state.aborted.store(true, SeqCst);
+        let task = self.state.task.lock().unwrap().take();
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> Abortable<T> {
+    /// Creates a new `Abortable` future or stream which is aborted through the
+    /// handle that `reg` was created with.
+    pub fn new(inner: T, reg: AbortRegistration) -> Abortable<T> {
+        Abortable {
+            inner: inner,
+            state: reg.state,
+            done: false,
+        }
+    }
+
+    /// Returns whether this future or stream has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.state.aborted.load(SeqCst)
+    }
+
+    // Called after the inner value returned `NotReady`. Arranges for the
+    // current task to be unparked by `abort`, and then checks whether we were
+    // aborted in the meantime to avoid missing the notification.
+    fn park(&self) -> bool {
+        *self.state.task.lock().unwrap() = Some(task::park());
+        self.is_aborted()
+    }
+}
+
+impl<F> Future for Abortable<F>
+    where F: Future,
+{
+    type Item = F::Item;
+    type Error = AbortError<F::Error>;
+
+    fn poll(&mut self) -> Poll<F::Item, AbortError<F::Error>> {
+        if self.is_aborted() {
+            return Err(AbortError::Aborted)
+        }
+        match self.inner.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(e)) => return Ok(Async::Ready(e)),
+            Err(e) => return Err(AbortError::Inner(e)),
+        }
+        if self.park() {
+            Err(AbortError::Aborted)
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl<S> Stream for Abortable<S>
+    where S: Stream,
+{
+    type Item = S::Item;
+    type Error = AbortError<S::Error>;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, AbortError<S::Error>> {
+        if self.done {
+            return Ok(Async::Ready(None))
+        }
+        if self.is_aborted() {
+            self.done = true;
+            return Err(AbortError::Aborted)
+        }
+        match self.inner.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(e)) => return Ok(Async::Ready(e)),
+            Err(e) => return Err(AbortError::Inner(e)),
+        }
+        if self.park() {
+            self.done = true;
+            Err(AbortError::Aborted)
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for AbortError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AbortError::Aborted => write!(fmt, "future was aborted"),
+            AbortError::Inner(ref e) => e.fmt(fmt),
+        }
+    }
+}
+
+impl<E: Error> Error for AbortError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            AbortError::Aborted => "future was aborted",
+            AbortError::Inner(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            AbortError::Aborted => None,
+            AbortError::Inner(ref e) => Some(e),
+        }
+    }
+}
@@ -70,16 +70,22 @@ pub use self::then::Then;
 pub use self::either::Either;
 
 if_std! {
+    mod abortable;
     mod catch_unwind;
     mod join_all;
+    mod retry;
     mod select_all;
     mod select_ok;
     mod shared;
+    mod timeout;
+    pub use self::abortable::{abortable, Abortable, AbortHandle, AbortRegistration, AbortError};
     pub use self::catch_unwind::CatchUnwind;
     pub use self::join_all::{join_all, JoinAll};
+    pub use self::retry::{retry, Retry, RetryPolicy};
     pub use self::select_all::{SelectAll, SelectAllNext, select_all};
     pub use self::select_ok::{SelectOk, select_ok};
     pub use self::shared::Shared;
+    pub use self::timeout::Timeout;
 
     #[doc(hidden)]
     #[deprecated(since = "0.1.4", note = "use join_all instead")]
@@ -807,6 +813,36 @@ pub trait Future {
     {
         Shared::new(self)
     }
+
+    /// Fails with `TimeoutError::Elapsed` if this future doesn't resolve
+    /// within `dur`.
+    ///
+    /// The deadline is tracked by the default `Timer` from the `timer` module,
+    /// starting from when this method is called. If the future completes in
+    /// time its result is passed through, with any error wrapped in
+    /// `TimeoutError::Inner`. Otherwise the future is dropped when the
+    /// returned future is, without being polled again. Use `Timeout::new` to
+    /// track the deadline with a different `Clock`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use futures::future::*;
+    /// use futures::timer::TimeoutError;
+    ///
+    /// let future = empty::<(), ()>().timeout(Duration::from_millis(10));
+    /// assert_eq!(future.wait(), Err(TimeoutError::Elapsed));
+    ///
+    /// let future = ok::<_, ()>(1).timeout(Duration::from_millis(10));
+    /// assert_eq!(future.wait(), Ok(1));
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn timeout(self, dur: ::std::time::Duration) -> Timeout<Self>
+        where Self: Sized
+    {
+        Timeout::new(self, dur, ::timer::Timer::default())
+    }
 }
 
 impl<'a, F: ?Sized + Future> Future for &'a mut F {
@@ -0,0 +1,300 @@
+//! Definition of the `Retry` combinator, re-running a future until it
+//! succeeds, along with the `RetryPolicy` deciding when and how often to do
+//! so.
+
+use std::prelude::v1::*;
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use {Future, IntoFuture, Poll, Async};
+use task;
+use timer::{Clock, Timer};
+
+/// A policy deciding whether, and after how long, a failed operation passed to
+/// `future::retry` or `stream::retry` is retried.
+///
+/// A policy is made up of a backoff, which is either `fixed` or `exponential`,
+/// along with optional jitter, a limit on the number of attempts and a
+/// predicate selecting which errors are worth retrying. By default every error
+/// is retried, forever.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use futures::future::RetryPolicy;
+///
+/// let policy = RetryPolicy::<std::io::Error>::exponential(Duration::from_millis(10))
+///     .max_delay(Duration::from_secs(1))
+///     .jitter()
+///     .max_attempts(5)
+///     .retry_if(|e| e.kind() == std::io::ErrorKind::Interrupted);
+/// # drop(policy);
+/// ```
+pub struct RetryPolicy<E> {
+    initial: Duration,
+    factor: u32,
+    max_delay: Option<Duration>,
+    jitter: Option<u64>,
+    max_attempts: Option<usize>,
+    retryable: Option<Box<Fn(&E) -> bool + Send>>,
+}
+
+impl<E> RetryPolicy<E> {
+    /// Creates a policy which waits `delay` before every retry.
+    pub fn fixed(delay: Duration) -> RetryPolicy<E> {
+        RetryPolicy::new(delay, 1)
+    }
+
+    /// Creates a policy which waits `initial` before the first retry, and
+    /// doubles the delay before each one after that.
+    ///
+    /// The factor the delay grows by can be changed with `factor`.
+    pub fn exponential(initial: Duration) -> RetryPolicy<E> {
+        RetryPolicy::new(initial, 2)
+    }
+
+    fn new(initial: Duration, factor: u32) -> RetryPolicy<E> {
+        RetryPolicy {
+            initial: initial,
+            factor: factor,
+            max_delay: None,
+            jitter: None,
+            max_attempts: None,
+            retryable: None,
+        }
+    }
+
+    /// Sets the factor an exponential delay grows by after each retry.
+    pub fn factor(mut self, factor: u32) -> RetryPolicy<E> {
+        self.factor = factor;
+        self
+    }
+
+    /// Caps the delay before any single retry at `max`.
+    pub fn max_delay(mut self, max: Duration) -> RetryPolicy<E> {
+        self.max_delay = Some(max);
+        self
+    }
+
+    /// Randomizes each delay to somewhere between half of it and all of it.
+    ///
+    /// This keeps many clients which failed at the same time from all
+    /// retrying at the same time as well.
+    pub fn jitter(mut self) -> RetryPolicy<E> {
+        // Seed a small xorshift generator from the standard library's random
+        // hash keys, making sure it doesn't start at zero where it'd get stuck
+        let seed = RandomState::new().build_hasher().finish();
+        self.jitter = Some(seed | 1);
+        self
+    }
+
+    /// Gives up once `attempts` attempts have failed, including the first.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `attempts` is zero.
+    pub fn max_attempts(mut self, attempts: usize) -> RetryPolicy<E> {
+        assert!(attempts > 0, "must make at least one attempt");
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Only retries errors for which `f` returns `true`, giving up
+    /// immediately on any other error.
+    pub fn retry_if<F>(mut self, f: F) -> RetryPolicy<E>
+        where F: Fn(&E) -> bool + Send + 'static,
+    {
+        self.retryable = Some(Box::new(f));
+        self
+    }
+
+    /// Returns how long to wait before retrying after `attempts` consecutive
+    /// attempts have failed, the last with `error`, or `None` to give up.
+    pub fn next_delay(&mut self, attempts: usize, error: &E) -> Option<Duration> {
+        if let Some(max) = self.max_attempts {
+            if attempts >= max {
+                return None
+            }
+        }
+        if let Some(ref f) = self.retryable {
+            if !f(error) {
+                return None
+            }
+        }
+
+        let mut delay = self.initial;
+        for _ in 1..attempts {
+            if self.factor <= 1 || delay == Duration::new(0, 0) {
+                break
+            }
+            match delay.checked_mul(self.factor) {
+                Some(d) => delay = d,
+                None => break,
+            }
+            if self.max_delay.map_or(false, |max| delay >= max) {
+                break
+            }
+        }
+        if let Some(max) = self.max_delay {
+            if delay > max {
+                delay = max;
+            }
+        }
+
+        if let Some(ref mut state) = self.jitter {
+            *state ^= *state << 13;
+            *state ^= *state >> 7;
+            *state ^= *state << 17;
+            let nanos = delay.as_secs()
+                .saturating_mul(1_000_000_000)
+                .saturating_add(delay.subsec_nanos() as u64);
+            let half = nanos / 2;
+            let nanos = nanos - half + *state % (half + 1);
+            delay = Duration::new(nanos / 1_000_000_000,
+                                  (nanos % 1_000_000_000) as u32);
+        }
+        Some(delay)
+    }
+}
+
+impl<E> fmt::Debug for RetryPolicy<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RetryPolicy")
+           .field("initial", &self.initial)
+           .field("factor", &self.factor)
+           .field("max_delay", &self.max_delay)
+           .field("jitter", &self.jitter.is_some())
+           .field("max_attempts", &self.max_attempts)
+           .finish()
+    }
+}
+
+/// Future for the `retry` combinator, re-running a future until it succeeds
+/// or its `RetryPolicy` gives up.
+///
+/// This is created by the `future::retry` function, which uses the default
+/// `Timer`, or by `Retry::new` with any other `Clock`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Retry<F, R, C = Timer> where R: IntoFuture, C: Clock {
+    factory: F,
+    policy: RetryPolicy<R::Error>,
+    clock: C,
+    attempts: usize,
+    state: State<R::Future, C::Delay>,
+}
+
+enum State<A, D> {
+    // The next attempt is yet to be started
+    Pending,
+    Running(A),
+    Waiting(D),
+}
+
+/// Creates a future which runs the future returned by `f`, calling `f` again
+/// and running its new future each time the previous one fails.
+///
+/// Nothing happens until the returned future is first polled, at which point
+/// `f` is called to start the first attempt.
+///
+/// After each failure `policy` decides whether to try again, and how long to
+/// wait before doing so. The returned future resolves to the result of the
+/// first attempt which succeeds, or to the error of the last attempt if the
+/// policy gives up. Delays between attempts are tracked by the default `Timer`
+/// from the `timer` module, and `Retry::new` can be used with another `Clock`.
+///
+/// Unlike chaining attempts together with `or_else`, the returned future runs
+/// in constant space no matter how many attempts are made. An attempt which is
+/// retried without a delay is started on the next poll rather than right
+/// away, so that the task gives other work a chance to run in between.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use futures::Future;
+/// use futures::future::{self, RetryPolicy};
+///
+/// let mut attempts = 0;
+/// let policy = RetryPolicy::fixed(Duration::from_millis(1)).max_attempts(5);
+/// let f = future::retry(policy, || {
+///     attempts += 1;
+///     if attempts < 3 { Err(attempts) } else { Ok(attempts) }
+/// });
+/// assert_eq!(f.wait(), Ok(3));
+/// ```
+pub fn retry<F, R>(policy: RetryPolicy<R::Error>, f: F) -> Retry<F, R>
+    where F: FnMut() -> R,
+          R: IntoFuture,
+{
+    Retry::new(policy, f, Timer::default())
+}
+
+impl<F, R, C> Retry<F, R, C>
+    where F: FnMut() -> R,
+          R: IntoFuture,
+          C: Clock,
+{
+    /// Creates a new future like `future::retry`, except that delays between
+    /// attempts are tracked by `clock`.
+    pub fn new(policy: RetryPolicy<R::Error>, f: F, clock: C) -> Retry<F, R, C> {
+        Retry {
+            factory: f,
+            policy: policy,
+            clock: clock,
+            attempts: 0,
+            state: State::Pending,
+        }
+    }
+}
+
+impl<F, R, C> Future for Retry<F, R, C>
+    where F: FnMut() -> R,
+          R: IntoFuture,
+          C: Clock,
+{
+    type Item = R::Item;
+    type Error = R::Error;
+
+    fn poll(&mut self) -> Poll<R::Item, R::Error> {
+        loop {
+            let next = match self.state {
+                State::Pending => State::Running((self.factory)().into_future()),
+                State::Running(ref mut f) => {
+                    let e = match f.poll() {
+                        Ok(a) => return Ok(a),
+                        Err(e) => e,
+                    };
+                    self.attempts += 1;
+                    match self.policy.next_delay(self.attempts, &e) {
+                        Some(d) if d > Duration::new(0, 0) => {
+                            let at = self.clock.now() + d;
+                            State::Waiting(self.clock.delay_until(at))
+                        }
+                        // Retry right away, but through the executor, so that
+                        // an attempt which keeps failing immediately doesn't
+                        // keep us from ever returning.
+                        Some(_) => {
+                            self.state = State::Pending;
+                            task::park().unpark();
+                            return Ok(Async::NotReady)
+                        }
+                        None => return Err(e),
+                    }
+                }
+                // If the timer fails we can't wait any longer, so go ahead
+                // with the next attempt right away.
+                State::Waiting(ref mut delay) => {
+                    match delay.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(())) | Err(_) => State::Pending,
+                    }
+                }
+            };
+            self.state = next;
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use timer::{Clock, Timer, TimeoutError};
+
+/// Future for the `timeout` combinator, which fails with
+/// `TimeoutError::Elapsed` if the underlying future doesn't resolve in time.
+///
+/// This is created by the `Future::timeout` method, which uses the default
+/// `Timer`, or by `Timeout::new` with any other `Clock`.
+#[must_use = "futures do nothing unless polled"]
+pub struct Timeout<F, C: Clock = Timer> {
+    future: F,
+    delay: C::Delay,
+}
+
+impl<F, C> Timeout<F, C>
+    where F: Future,
+          C: Clock,
+{
+    /// Creates a new future which resolves to the result of `future`, or fails
+    /// once `dur` has elapsed according to `clock`.
+    ///
+    /// If the clock's timer fails then the deadline can no longer be tracked,
+    /// and the future also fails with `TimeoutError::Elapsed`.
+    pub fn new(future: F, dur: Duration, clock: C) -> Timeout<F, C> {
+        let deadline = clock.now() + dur;
+        Timeout {
+            future: future,
+            delay: clock.delay_until(deadline),
+        }
+    }
+}
+
+impl<F, C> Future for Timeout<F, C>
+    where F: Future,
+          C: Clock,
+{
+    type Item = F::Item;
+    type Error = TimeoutError<F::Error>;
+
+    fn poll(&mut self) -> Poll<F::Item, TimeoutError<F::Error>> {
+        match self.future.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(e)) => return Ok(Async::Ready(e)),
+            Err(e) => return Err(TimeoutError::Inner(e)),
+        }
+        match self.delay.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(())) | Err(_) => Err(TimeoutError::Elapsed),
+        }
+    }
+}
@@ -200,6 +200,7 @@ if_std! {
     pub mod task;
     pub mod executor;
     pub mod sync;
+    pub mod timer;
 
     #[doc(hidden)]
     #[deprecated(since = "0.1.4", note = "use sync::oneshot::channel instead")]
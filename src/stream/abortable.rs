@@ -0,0 +1,31 @@
+use future::{Abortable, AbortHandle};
+use stream::Stream;
+
+/// Creates a new `Abortable` stream and a handle which can be used to abort
+/// it.
+///
+/// Once `abort` is called on the returned handle, the stream will yield
+/// `AbortError::Aborted` the next time it's polled and then terminate. The
+/// task it's running on will be unparked so that happens promptly, and the
+/// underlying stream is no longer polled after it's been aborted.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Stream;
+/// use futures::stream;
+/// use futures::future::AbortError;
+///
+/// let (stream, handle) = stream::abortable(stream::iter(vec![Ok::<_, ()>(1)]));
+/// handle.abort();
+///
+/// let mut stream = stream.wait();
+/// assert_eq!(stream.next(), Some(Err(AbortError::Aborted)));
+/// assert_eq!(stream.next(), None);
+/// ```
+pub fn abortable<S>(stream: S) -> (Abortable<S>, AbortHandle)
+    where S: Stream,
+{
+    let (handle, reg) = AbortHandle::new_pair();
+    (Abortable::new(stream, reg), handle)
+}
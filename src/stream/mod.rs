@@ -74,6 +74,7 @@ use sink::{Sink};
 if_std! {
     use std;
 
+    mod abortable;
     mod buffered;
     mod buffer_unordered;
     mod catch_unwind;
@@ -83,6 +84,9 @@ if_std! {
     mod channel;
     mod split;
     mod futures_unordered;
+    mod retry;
+    mod timeout;
+    pub use self::abortable::abortable;
     pub use self::buffered::Buffered;
     pub use self::buffer_unordered::BufferUnordered;
     pub use self::catch_unwind::CatchUnwind;
@@ -91,6 +95,8 @@ if_std! {
     pub use self::wait::Wait;
     pub use self::split::{SplitStream, SplitSink};
     pub use self::futures_unordered::{futures_unordered, FuturesUnordered};
+    pub use self::retry::{retry, Retry};
+    pub use self::timeout::Timeout;
 
     #[doc(hidden)]
     #[cfg(feature = "with-deprecated")]
@@ -707,6 +713,39 @@ pub trait Stream {
         catch_unwind::new(self)
     }
 
+    /// Yields `TimeoutError::Elapsed` whenever this stream doesn't produce
+    /// its next item within `dur`.
+    ///
+    /// The deadline is tracked by the default `Timer` from the `timer` module.
+    /// The deadline for the first item starts when this method is called, and
+    /// each one after that starts when the previous item or error is yielded.
+    /// Items are passed through, with errors wrapped in `TimeoutError::Inner`.
+    /// The stream carries on after a deadline elapses, so a caller that wants
+    /// to give up should stop polling it. Use `Timeout::new` to track the
+    /// deadlines with a different `Clock`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use futures::stream::Stream;
+    /// use futures::sync::mpsc;
+    /// use futures::timer::TimeoutError;
+    ///
+    /// let (tx, rx) = mpsc::channel::<i32>(1);
+    /// let mut items = rx.timeout(Duration::from_millis(10)).wait();
+    /// assert_eq!(items.next(), Some(Err(TimeoutError::Elapsed)));
+    ///
+    /// drop(tx);
+    /// assert_eq!(items.next(), None);
+    /// ```
+    #[cfg(feature = "use_std")]
+    fn timeout(self, dur: std::time::Duration) -> Timeout<Self>
+        where Self: Sized
+    {
+        Timeout::new(self, dur, ::timer::Timer::default())
+    }
+
     /// An adaptor for creating a buffered list of pending futures.
     ///
     /// If this stream's item can be converted into a future, then this adaptor
@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use future::RetryPolicy;
+use stream::Stream;
+use task;
+use timer::{Clock, Timer};
+
+/// Stream for the `retry` combinator, re-creating a stream each time it fails
+/// until its `RetryPolicy` gives up.
+///
+/// This is created by the `stream::retry` function, which uses the default
+/// `Timer`, or by `Retry::new` with any other `Clock`.
+#[must_use = "streams do nothing unless polled"]
+pub struct Retry<F, S, C = Timer> where S: Stream, C: Clock {
+    factory: F,
+    policy: RetryPolicy<S::Error>,
+    clock: C,
+    attempts: usize,
+    state: State<S, C::Delay>,
+}
+
+enum State<S, D> {
+    // The next stream is yet to be created
+    Pending,
+    Running(S),
+    Waiting(D),
+    Done,
+}
+
+/// Creates a stream which yields the items of the stream returned by `f`,
+/// calling `f` again to re-subscribe each time that stream fails.
+///
+/// After each error `policy` decides whether to try again, and how long to
+/// wait before doing so. Errors which are retried aren't yielded from the
+/// returned stream. Once the policy gives up the last error is yielded and the
+/// stream ends, and it also ends as soon as one of the streams created by `f`
+/// does. Delays between attempts are tracked by the default `Timer` from the
+/// `timer` module, and `Retry::new` can be used with another `Clock`. A stream
+/// which is retried without a delay is re-created on the next poll rather than
+/// right away.
+///
+/// The number of attempts seen by the policy counts consecutive failures, and
+/// is reset whenever a stream yields an item.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use futures::Future;
+/// use futures::future::RetryPolicy;
+/// use futures::stream::{self, Stream};
+///
+/// let mut subscriptions = 0;
+/// let policy = RetryPolicy::fixed(Duration::from_millis(1));
+/// let s = stream::retry(policy, || {
+///     subscriptions += 1;
+///     let items = if subscriptions < 3 {
+///         vec![Ok(subscriptions), Err(())]
+///     } else {
+///         vec![Ok(subscriptions)]
+///     };
+///     stream::iter(items)
+/// });
+/// assert_eq!(s.collect().wait(), Ok(vec![1, 2, 3]));
+/// ```
+pub fn retry<F, S>(policy: RetryPolicy<S::Error>, f: F) -> Retry<F, S>
+    where F: FnMut() -> S,
+          S: Stream,
+{
+    Retry::new(policy, f, Timer::default())
+}
+
+impl<F, S, C> Retry<F, S, C>
+    where F: FnMut() -> S,
+          S: Stream,
+          C: Clock,
+{
+    /// Creates a new stream like `stream::retry`, except that delays between
+    /// attempts are tracked by `clock`.
+    pub fn new(policy: RetryPolicy<S::Error>, f: F, clock: C) -> Retry<F, S, C> {
+        Retry {
+            factory: f,
+            policy: policy,
+            clock: clock,
+            attempts: 0,
+            state: State::Pending,
+        }
+    }
+}
+
+impl<F, S, C> Stream for Retry<F, S, C>
+    where F: FnMut() -> S,
+          S: Stream,
+          C: Clock,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        loop {
+            let next = match self.state {
+                State::Pending => State::Running((self.factory)()),
+                State::Running(ref mut s) => {
+                    match s.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(Some(item))) => {
+                            self.attempts = 0;
+                            return Ok(Async::Ready(Some(item)))
+                        }
+                        Ok(Async::Ready(None)) => State::Done,
+                        Err(e) => {
+                            self.attempts += 1;
+                            match self.policy.next_delay(self.attempts, &e) {
+                                Some(d) if d > Duration::new(0, 0) => {
+                                    let at = self.clock.now() + d;
+                                    State::Waiting(self.clock.delay_until(at))
+                                }
+                                Some(_) => {
+                                    self.state = State::Pending;
+                                    task::park().unpark();
+                                    return Ok(Async::NotReady)
+                                }
+                                None => {
+                                    self.state = State::Done;
+                                    return Err(e)
+                                }
+                            }
+                        }
+                    }
+                }
+                // If the timer fails we can't wait any longer, so go ahead
+                // with the next attempt right away.
+                State::Waiting(ref mut delay) => {
+                    match delay.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(())) | Err(_) => State::Pending,
+                    }
+                }
+                State::Done => return Ok(Async::Ready(None)),
+            };
+            self.state = next;
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use {Future, Poll, Async};
+use stream::Stream;
+use timer::{Clock, Timer, TimeoutError};
+
+/// Stream for the `timeout` combinator, which yields
+/// `TimeoutError::Elapsed` whenever the underlying stream takes too long to
+/// produce its next item.
+///
+/// This is created by the `Stream::timeout` method, which uses the default
+/// `Timer`, or by `Timeout::new` with any other `Clock`.
+#[must_use = "streams do nothing unless polled"]
+pub struct Timeout<S, C: Clock = Timer> {
+    stream: S,
+    clock: C,
+    dur: Duration,
+    delay: C::Delay,
+}
+
+impl<S, C> Timeout<S, C>
+    where S: Stream,
+          C: Clock,
+{
+    /// Creates a new stream which yields the items of `stream`, or an error
+    /// if `dur` elapses according to `clock` before the next one arrives.
+    ///
+    /// The deadline for the first item starts when this is called, and the
+    /// deadline for each following item starts when the one before it is
+    /// yielded, whether that's an item, an error or `TimeoutError::Elapsed`.
+    /// As with other errors the stream keeps going after its deadline
+    /// elapses, so it's up to the caller to decide whether to stop.
+    pub fn new(stream: S, dur: Duration, clock: C) -> Timeout<S, C> {
+        let delay = clock.delay_until(clock.now() + dur);
+        Timeout {
+            stream: stream,
+            clock: clock,
+            dur: dur,
+            delay: delay,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.delay = self.clock.delay_until(self.clock.now() + self.dur);
+    }
+}
+
+impl<S, C> Stream for Timeout<S, C>
+    where S: Stream,
+          C: Clock,
+{
+    type Item = S::Item;
+    type Error = TimeoutError<S::Error>;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, TimeoutError<S::Error>> {
+        match self.stream.poll() {
+            Ok(Async::NotReady) => {}
+            Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+            Ok(Async::Ready(Some(e))) => {
+                self.reset();
+                return Ok(Async::Ready(Some(e)))
+            }
+            Err(e) => {
+                self.reset();
+                return Err(TimeoutError::Inner(e))
+            }
+        }
+        match self.delay.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(())) | Err(_) => {
+                self.reset();
+                Err(TimeoutError::Elapsed)
+            }
+        }
+    }
+}
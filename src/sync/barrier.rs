@@ -0,0 +1,201 @@
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware barrier which enables a number of tasks to synchronize the
+/// beginning of some computation.
+///
+/// This is the futures counterpart of `std::sync::Barrier`: rather than
+/// blocking a thread, `wait` returns a future which resolves once `n` tasks
+/// have arrived at the barrier. The barrier can then be reused for the next
+/// phase.
+///
+/// Handles to a `Barrier` are cheap to clone, and all clones refer to the same
+/// barrier.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use futures::Future;
+/// use futures::sync::Barrier;
+///
+/// let barrier = Barrier::new(10);
+/// let handles = (0..10).map(|_| {
+///     let barrier = barrier.clone();
+///     thread::spawn(move || {
+///         barrier.wait().wait().unwrap().is_leader()
+///     })
+/// }).collect::<Vec<_>>();
+///
+/// let leaders = handles.into_iter()
+///                      .map(|h| h.join().unwrap())
+///                      .filter(|&l| l)
+///                      .count();
+/// assert_eq!(leaders, 1);
+/// ```
+#[derive(Clone)]
+pub struct Barrier {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    n: usize,
+    state: sync::Mutex<State>,
+}
+
+struct State {
+    // Number of tasks which have arrived in the current generation
+    arrived: usize,
+    // Incremented every time the barrier is released
+    generation: usize,
+    waiters: Vec<(usize, Task)>,
+    next_id: usize,
+}
+
+/// Future returned by `Barrier::wait` which will resolve once all tasks have
+/// arrived at the barrier.
+#[must_use = "futures do nothing unless polled"]
+pub struct BarrierWait {
+    barrier: Barrier,
+    // Our id and the generation we arrived in, once we've arrived
+    arrived: Option<(usize, usize)>,
+}
+
+/// The result of waiting on a `Barrier`, which reports whether this task was
+/// the leader.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl Barrier {
+    /// Creates a new barrier which releases waiting tasks once `n` of them
+    /// have arrived.
+    ///
+    /// As with `std::sync::Barrier`, a barrier created with an `n` of 0
+    /// behaves the same as one created with an `n` of 1.
+    pub fn new(n: usize) -> Barrier {
+        Barrier {
+            inner: Arc::new(Inner {
+                n: n,
+                state: sync::Mutex::new(State {
+                    arrived: 0,
+                    generation: 0,
+                    waiters: Vec::new(),
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Returns a future which arrives at the barrier when first polled, and
+    /// resolves once `n` tasks have arrived.
+    ///
+    /// Exactly one of the tasks released together is reported as the leader
+    /// through `BarrierWaitResult::is_leader`: the one which arrived last.
+    ///
+    /// If the returned future is dropped after arriving but before the barrier
+    /// is released, its arrival is withdrawn. The returned future will never
+    /// resolve to an error.
+    pub fn wait(&self) -> BarrierWait {
+        BarrierWait {
+            barrier: self.clone(),
+            arrived: None,
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.inner.state.lock().unwrap();
+        fmt.debug_struct("Barrier")
+           .field("n", &self.inner.n)
+           .field("arrived", &state.arrived)
+           .finish()
+    }
+}
+
+impl Future for BarrierWait {
+    type Item = BarrierWaitResult;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<BarrierWaitResult, ()> {
+        let tasks = {
+            let mut state = self.barrier.inner.state.lock().unwrap();
+            if let Some((id, generation)) = self.arrived {
+                if state.generation != generation {
+                    self.arrived = None;
+                    return Ok(Async::Ready(BarrierWaitResult { is_leader: false }))
+                }
+                for &mut (w, ref mut task) in state.waiters.iter_mut() {
+                    if w == id && !task.is_current() {
+                        *task = task::park();
+                    }
+                }
+                return Ok(Async::NotReady)
+            }
+
+            state.arrived += 1;
+            if state.arrived < self.barrier.inner.n {
+                let id = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiters.push((id, task::park()));
+                self.arrived = Some((id, state.generation));
+                return Ok(Async::NotReady)
+            }
+
+            // We're the last to arrive, so release everyone else and start
+            // the next generation.
+            state.arrived = 0;
+            state.generation = state.generation.wrapping_add(1);
+            state.waiters.drain(..).map(|(_, task)| task).collect::<Vec<_>>()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+        Ok(Async::Ready(BarrierWaitResult { is_leader: true }))
+    }
+}
+
+impl Drop for BarrierWait {
+    fn drop(&mut self) {
+        if let Some((id, generation)) = self.arrived {
+            let mut state = self.barrier.inner.state.lock().unwrap();
+            if state.generation == generation {
+                state.arrived -= 1;
+                state.waiters.retain(|&(w, _)| w != id);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BarrierWait {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BarrierWait")
+           .field("arrived", &self.arrived.is_some())
+           .finish()
+    }
+}
+
+impl BarrierWaitResult {
+    /// Returns whether this task is the leader of the tasks released from the
+    /// barrier together.
+    ///
+    /// Exactly one task is the leader each time the barrier is released.
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<Barrier>();
+    _assert_sync::<Barrier>();
+    _assert_send::<BarrierWait>();
+}
@@ -0,0 +1,311 @@
+//! A multi-producer, multi-consumer, futures-aware broadcast channel.
+//!
+//! Every message sent on a broadcast channel is delivered to every `Receiver`
+//! which exists at the time it's sent. Receivers implement `Stream`, and can
+//! be cloned to create more subscribers. New receivers can also be created
+//! from a `Sender` through `subscribe`, which only see messages sent after
+//! they were created.
+//!
+//! # Lagging
+//!
+//! Sending on a broadcast channel never waits for receivers. Instead the
+//! channel keeps the last `capacity` messages sent, and a receiver which
+//! falls so far behind that messages it hasn't seen yet are overwritten
+//! yields a `Lagged` error saying how many messages it missed. The receiver
+//! then continues with the oldest message still in the channel.
+//!
+//! # Disconnection
+//!
+//! When all `Sender` handles have been dropped, receivers yield the messages
+//! remaining in the channel which they haven't seen yet and then terminate.
+//! If there are no receivers left, `send` fails and hands back the message.
+
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use task::{self, Task};
+use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+
+/// The transmission end of a broadcast channel which is used to send values.
+///
+/// This is created by the `channel` function.
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// The receiving end of a broadcast channel, which is a `Stream` of all
+/// messages sent on the channel.
+///
+/// This is created by the `channel` function or `Sender::subscribe`. Cloning
+/// a receiver creates another subscriber which will see the same messages as
+/// the original from then on, including any the original hasn't yet seen.
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+    // Sequence number of the next message this receiver will see
+    next: u64,
+    id: usize,
+}
+
+/// Error returned from a `Receiver` which has fallen behind, carrying the
+/// number of messages it missed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Lagged(pub u64);
+
+/// Error type for sending, used when there are no receivers left to receive
+/// the message.
+pub struct SendError<T>(T);
+
+struct Inner<T> {
+    // The last `capacity` messages sent on the channel
+    buffer: VecDeque<T>,
+    capacity: usize,
+    // Sequence number of the first message in `buffer`
+    head: u64,
+    num_senders: usize,
+    num_receivers: usize,
+    // Receivers which are waiting for a message, keyed by their id
+    waiters: Vec<(usize, Task)>,
+    next_id: usize,
+}
+
+/// Creates a broadcast channel which keeps up to `capacity` messages for
+/// receivers which have yet to see them.
+///
+/// # Panics
+///
+/// This function panics if `capacity` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use futures::{Future, Stream};
+/// use futures::sync::broadcast;
+///
+/// let (tx, rx1) = broadcast::channel(16);
+/// let rx2 = rx1.clone();
+///
+/// tx.send(1).unwrap();
+/// tx.send(2).unwrap();
+/// drop(tx);
+///
+/// assert_eq!(rx1.collect().wait(), Ok(vec![1, 2]));
+/// assert_eq!(rx2.collect().wait(), Ok(vec![1, 2]));
+/// ```
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "capacity must be non-zero");
+    let inner = Arc::new(Mutex::new(Inner {
+        buffer: VecDeque::with_capacity(capacity),
+        capacity: capacity,
+        head: 0,
+        num_senders: 1,
+        num_receivers: 1,
+        waiters: Vec::new(),
+        next_id: 1,
+    }));
+    let tx = Sender { inner: inner.clone() };
+    let rx = Receiver { inner: inner, next: 0, id: 0 };
+    (tx, rx)
+}
+
+impl<T> Inner<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+
+    fn new_receiver(inner: &Arc<Mutex<Inner<T>>>, next: u64) -> Receiver<T> {
+        let mut state = inner.lock().unwrap();
+        state.num_receivers += 1;
+        let id = state.next_id;
+        state.next_id += 1;
+        Receiver { inner: inner.clone(), next: next, id: id }
+    }
+
+    fn take_waiters(&mut self) -> Vec<Task> {
+        self.waiters.drain(..).map(|(_, task)| task).collect()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Sends a message to all receivers of this channel.
+    ///
+    /// This never waits: if the channel is full the oldest message is
+    /// overwritten, and receivers which haven't seen it yet will report that
+    /// they lagged behind. Fails only if there are no receivers left, in
+    /// which case the message is handed back.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            if state.num_receivers == 0 {
+                return Err(SendError(msg))
+            }
+            if state.buffer.len() == state.capacity {
+                state.buffer.pop_front();
+                state.head += 1;
+            }
+            state.buffer.push_back(msg);
+            state.take_waiters()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+        Ok(())
+    }
+
+    /// Creates a new receiver which will see all messages sent after this
+    /// call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let tail = self.inner.lock().unwrap().tail();
+        Inner::new_receiver(&self.inner, tail)
+    }
+
+    /// Returns the number of receivers of this channel.
+    pub fn receiver_count(&self) -> usize {
+        self.inner.lock().unwrap().num_receivers
+    }
+}
+
+impl<T> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, msg: T) -> StartSend<T, SendError<T>> {
+        try!(Sender::send(self, msg));
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        self.inner.lock().unwrap().num_senders += 1;
+        Sender { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            state.num_senders -= 1;
+            if state.num_senders > 0 {
+                return
+            }
+            state.take_waiters()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+    type Error = Lagged;
+
+    fn poll(&mut self) -> Poll<Option<T>, Lagged> {
+        let mut state = self.inner.lock().unwrap();
+        if self.next < state.head {
+            let missed = state.head - self.next;
+            self.next = state.head;
+            return Err(Lagged(missed))
+        }
+        if self.next < state.tail() {
+            let msg = state.buffer[(self.next - state.head) as usize].clone();
+            self.next += 1;
+            return Ok(Async::Ready(Some(msg)))
+        }
+        if state.num_senders == 0 {
+            return Ok(Async::Ready(None))
+        }
+
+        let task = task::park();
+        let id = self.id;
+        match state.waiters.iter().position(|&(w, _)| w == id) {
+            Some(pos) => state.waiters[pos].1 = task,
+            None => state.waiters.push((id, task)),
+        }
+        Ok(Async::NotReady)
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        Inner::new_receiver(&self.inner, self.next)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.inner.lock().unwrap();
+        state.num_receivers -= 1;
+        let id = self.id;
+        state.waiters.retain(|&(w, _)| w != id);
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sender").finish()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Receiver").finish()
+    }
+}
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "receiver lagged behind by {} messages", self.0)
+    }
+}
+
+impl Error for Lagged {
+    fn description(&self) -> &str {
+        "receiver lagged behind"
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because there are no receivers")
+    }
+}
+
+impl<T> Error for SendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        "send failed because there are no receivers"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<Sender<u32>>();
+    _assert_send::<Receiver<u32>>();
+}
@@ -0,0 +1,357 @@
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::mem;
+use std::sync::{self, Arc, Weak};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+
+use {Async, Future, Poll};
+use stream::Stream;
+use task::{self, Task};
+
+/// A token which can be used to signal cancellation to any number of tasks,
+/// organized in a tree.
+///
+/// Calling `cancel` on a token cancels it along with every token created from
+/// it through `child_token`, recursively, while cancelling a child token
+/// leaves its parent alone. This makes it possible to shut down a whole
+/// subtree of tasks at once, for example all the tasks spawned onto a pool
+/// for one request.
+///
+/// Tasks can find out about cancellation through `is_cancelled`, by waiting
+/// on the `cancelled` future, or by wrapping a future or stream with
+/// `cancellable` so it's dropped or ended when the token is cancelled.
+///
+/// Handles to a `CancellationToken` are cheap to clone, and all clones refer
+/// to the same token.
+///
+/// # Examples
+///
+/// ```
+/// use futures::{Future, Stream};
+/// use futures::future;
+/// use futures::stream;
+/// use futures::sync::CancellationToken;
+///
+/// let token = CancellationToken::new();
+/// let child = token.child_token();
+///
+/// let work = child.cancellable(future::empty::<(), ()>());
+/// let events = child.cancellable(stream::repeat::<_, ()>(1));
+///
+/// token.cancel();
+/// assert!(child.is_cancelled());
+/// assert_eq!(work.wait(), Ok(None));
+/// assert_eq!(events.collect().wait(), Ok(vec![]));
+/// ```
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    // Only ever set while `state` is locked, so that waiters and children
+    // registered under the lock never miss it
+    cancelled: AtomicBool,
+    state: sync::Mutex<State>,
+}
+
+struct State {
+    waiters: Vec<(usize, Task)>,
+    children: Vec<Weak<Inner>>,
+    next_id: usize,
+}
+
+impl CancellationToken {
+    /// Creates a new token which isn't cancelled and has no parent.
+    pub fn new() -> CancellationToken {
+        CancellationToken::with_cancelled(false)
+    }
+
+    fn with_cancelled(cancelled: bool) -> CancellationToken {
+        CancellationToken {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(cancelled),
+                state: sync::Mutex::new(State {
+                    waiters: Vec::new(),
+                    children: Vec::new(),
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Creates a new token which is cancelled whenever this one is.
+    ///
+    /// Cancelling the returned token has no effect on this one. If this token
+    /// is already cancelled, the returned token is too.
+    pub fn child_token(&self) -> CancellationToken {
+        let mut state = self.inner.state.lock().unwrap();
+        if self.is_cancelled() {
+            return CancellationToken::with_cancelled(true)
+        }
+        let child = CancellationToken::new();
+        // Forget about children which have since been dropped
+        state.children.retain(|c| c.upgrade().is_some());
+        state.children.push(Arc::downgrade(&child.inner));
+        child
+    }
+
+    /// Cancels this token and all of its children, waking up every task
+    /// waiting on any of them.
+    ///
+    /// Calling this more than once has no further effect.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Returns whether this token has been cancelled, either directly or
+    /// through one of its ancestors.
+    ///
+    /// This function doesn't require a task context.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(SeqCst)
+    }
+
+    /// Returns a future which resolves once this token is cancelled.
+    ///
+    /// The returned future will never resolve to an error.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            token: self.clone(),
+            id: None,
+        }
+    }
+
+    /// Wraps a future or stream so that it's stopped once this token is
+    /// cancelled.
+    ///
+    /// A wrapped future resolves to `Some` with its value if it completes
+    /// first, or to `None` if the token is cancelled first. A wrapped stream
+    /// ends once the token is cancelled. In both cases the inner future or
+    /// stream is dropped as soon as cancellation is noticed, and is never
+    /// polled again.
+    pub fn cancellable<T>(&self, inner: T) -> Cancellable<T> {
+        Cancellable {
+            inner: Some(inner),
+            token: self.clone(),
+            id: None,
+        }
+    }
+
+    // Registers the current task to be woken up on cancellation under the
+    // waiter `id`, unless the token is already cancelled.
+    fn park(&self, id: &mut Option<usize>) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+        if self.is_cancelled() {
+            return true
+        }
+        let task = task::park();
+        match *id {
+            Some(me) => {
+                for &mut (w, ref mut t) in state.waiters.iter_mut() {
+                    if w == me {
+                        *t = task;
+                        return false
+                    }
+                }
+                state.waiters.push((me, task));
+            }
+            None => {
+                let me = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiters.push((me, task));
+                *id = Some(me);
+            }
+        }
+        false
+    }
+
+    fn unregister(&self, id: usize) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.waiters.retain(|&(w, _)| w != id);
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> CancellationToken {
+        CancellationToken::new()
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CancellationToken")
+           .field("cancelled", &self.is_cancelled())
+           .finish()
+    }
+}
+
+impl Inner {
+    fn cancel(&self) {
+        let (waiters, children) = {
+            let mut state = self.state.lock().unwrap();
+            if self.cancelled.swap(true, SeqCst) {
+                return
+            }
+            (mem::replace(&mut state.waiters, Vec::new()),
+             mem::replace(&mut state.children, Vec::new()))
+        };
+        for (_, task) in waiters {
+            task.unpark();
+        }
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled` which will resolve once
+/// the token is cancelled.
+#[must_use = "futures do nothing unless polled"]
+pub struct Cancelled {
+    token: CancellationToken,
+    id: Option<usize>,
+}
+
+impl Future for Cancelled {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        if self.token.park(&mut self.id) {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.token.unregister(id);
+        }
+    }
+}
+
+impl fmt::Debug for Cancelled {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Cancelled")
+           .field("token", &self.token)
+           .finish()
+    }
+}
+
+/// A future or stream which is stopped once a `CancellationToken` is
+/// cancelled.
+///
+/// This is created by the `CancellationToken::cancellable` method.
+#[must_use = "futures do nothing unless polled"]
+pub struct Cancellable<T> {
+    // Dropped once cancellation has been noticed
+    inner: Option<T>,
+    token: CancellationToken,
+    id: Option<usize>,
+}
+
+impl<T> Cancellable<T> {
+    /// Returns whether this future or stream has been stopped by its token
+    /// being cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.is_none()
+    }
+
+    // Called after the inner value returned `NotReady`. Arranges for the
+    // current task to be unparked on cancellation, dropping the inner value
+    // if the token was cancelled in the meantime.
+    fn park(&mut self) -> bool {
+        if self.token.park(&mut self.id) {
+            self.inner = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<F> Future for Cancellable<F>
+    where F: Future,
+{
+    type Item = Option<F::Item>;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Option<F::Item>, F::Error> {
+        if self.token.is_cancelled() {
+            self.inner = None;
+        }
+        match self.inner {
+            Some(ref mut f) => {
+                if let Async::Ready(e) = try!(f.poll()) {
+                    return Ok(Async::Ready(Some(e)))
+                }
+            }
+            None => return Ok(Async::Ready(None)),
+        }
+        if self.park() {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl<S> Stream for Cancellable<S>
+    where S: Stream,
+{
+    type Item = S::Item;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<S::Item>, S::Error> {
+        if self.token.is_cancelled() {
+            self.inner = None;
+        }
+        match self.inner {
+            Some(ref mut s) => {
+                if let Async::Ready(e) = try!(s.poll()) {
+                    return Ok(Async::Ready(e))
+                }
+            }
+            None => return Ok(Async::Ready(None)),
+        }
+        if self.park() {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl<T> Drop for Cancellable<T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.token.unregister(id);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Cancellable<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Cancellable")
+           .field("inner", &self.inner)
+           .field("token", &self.token)
+           .finish()
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<CancellationToken>();
+    _assert_sync::<CancellationToken>();
+    _assert_send::<Cancelled>();
+    _assert_send::<Cancellable<()>>();
+}
@@ -7,6 +7,28 @@
 
 pub mod oneshot;
 pub mod mpsc;
+pub mod mpmc;
+pub mod priority;
+pub mod broadcast;
+pub mod watch;
+mod barrier;
 mod bilock;
+mod cancellation_token;
+mod mutex;
+mod notify;
+mod once_cell;
+mod rwlock;
+mod semaphore;
+mod wait_group;
 
+pub use self::barrier::{Barrier, BarrierWait, BarrierWaitResult};
 pub use self::bilock::{BiLock, BiLockGuard, BiLockAcquire, BiLockAcquired};
+pub use self::cancellation_token::{CancellationToken, Cancelled, Cancellable};
+pub use self::mutex::{Mutex, MutexGuard, MutexLock, MutexAcquire, MutexAcquired};
+pub use self::notify::{Notify, Notified};
+pub use self::once_cell::{OnceCell, OnceCellGetOrInit, OnceCellGetOrInitCloned};
+pub use self::rwlock::{RwLock, Preference, RwLockReadGuard, RwLockWriteGuard};
+pub use self::rwlock::{RwLockRead, RwLockWrite, RwLockReadAcquire, RwLockWriteAcquire};
+pub use self::rwlock::{RwLockReadAcquired, RwLockWriteAcquired};
+pub use self::semaphore::{Semaphore, SemaphoreAcquire, SemaphorePermit};
+pub use self::wait_group::{WaitGroup, WaitGroupWait};
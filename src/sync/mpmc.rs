@@ -0,0 +1,340 @@
+//! A multi-producer, multi-consumer, futures-aware, FIFO queue with back
+//! pressure.
+//!
+//! This channel works like the one in the `mpsc` module, except that the
+//! `Receiver` can be cloned as well. Each message sent on the channel is
+//! delivered to exactly one of the receivers, which makes this channel
+//! suitable as a work queue shared by a number of consumer tasks.
+//!
+//! As with `mpsc`, the channel capacity is `buffer + num-senders`: each
+//! sender gets a guaranteed slot, and once the channel is at capacity a
+//! sender which sends a message is parked until a receiver takes a message
+//! out of the channel.
+//!
+//! # Disconnection
+//!
+//! When all `Sender` handles have been dropped, or the channel has been
+//! closed, it is no longer possible to send values into the channel. Once the
+//! messages remaining in the channel have been received, every `Receiver` will
+//! return `Ok(Ready(None))`.
+//!
+//! If all receiver handles are dropped, then messages can no longer be read
+//! out of the channel. In this case, a `send` will result in an error.
+//!
+//! # Clean Shutdown
+//!
+//! Any receiver can call `close`, which prevents any further messages from
+//! being sent into the channel, after which the receivers can consume the
+//! channel to completion before being dropped.
+
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use task::{self, Task};
+use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+
+/// The transmission end of a channel which is used to send values.
+///
+/// This is created by the `channel` function.
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+    id: usize,
+}
+
+/// The receiving end of a channel which implements the `Stream` trait.
+///
+/// This is created by the `channel` function. Cloning a receiver creates
+/// another consumer of the same channel; each message is received by only one
+/// of them.
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+    id: usize,
+}
+
+/// Error type for sending, used when the channel has been closed or all
+/// receivers have been dropped.
+pub struct SendError<T>(T);
+
+struct Inner<T> {
+    buffer: usize,
+    messages: VecDeque<T>,
+    // False once a receiver has called `close` or all receivers are gone
+    is_open: bool,
+    num_senders: usize,
+    num_receivers: usize,
+    // Senders which sent a message while the channel was at capacity, in the
+    // order in which they'll be unparked
+    parked_senders: VecDeque<(usize, Task)>,
+    // Receivers waiting for a message, in the order in which they'll be
+    // notified
+    recv_tasks: VecDeque<(usize, Task)>,
+    next_id: usize,
+}
+
+/// Creates an in-memory multi-consumer channel with bounded capacity.
+///
+/// The channel capacity is equal to `buffer + num-senders`. In other words,
+/// each sender gets a guaranteed slot in the channel capacity, and on top of
+/// that there are `buffer` "first come, first serve" slots available to all
+/// senders.
+///
+/// # Examples
+///
+/// ```
+/// use futures::{Future, Sink, Stream};
+/// use futures::sync::mpmc;
+///
+/// let (tx, rx1) = mpmc::channel(4);
+/// let rx2 = rx1.clone();
+///
+/// let tx = tx.send(1).wait().unwrap();
+/// let tx = tx.send(2).wait().unwrap();
+/// drop(tx);
+///
+/// let mut rx1 = rx1.wait();
+/// assert_eq!(rx1.next(), Some(Ok(1)));
+/// assert_eq!(rx2.collect().wait(), Ok(vec![2]));
+/// ```
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        buffer: buffer,
+        messages: VecDeque::new(),
+        is_open: true,
+        num_senders: 1,
+        num_receivers: 1,
+        parked_senders: VecDeque::new(),
+        recv_tasks: VecDeque::new(),
+        next_id: 2,
+    }));
+    let tx = Sender { inner: inner.clone(), id: 0 };
+    let rx = Receiver { inner: inner, id: 1 };
+    (tx, rx)
+}
+
+impl<T> Inner<T> {
+    fn next_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+    }
+
+    // Called when the channel is closed or loses all of its senders, waking
+    // up everything blocked on it so they can observe that.
+    fn take_all_tasks(&mut self) -> Vec<Task> {
+        let senders = self.parked_senders.drain(..);
+        let receivers = self.recv_tasks.drain(..);
+        senders.chain(receivers).map(|(_, task)| task).collect()
+    }
+}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    // Returns whether this sender is still waiting to be unparked, updating
+    // the task to notify if so.
+    fn is_parked(&self, state: &mut Inner<T>) -> bool {
+        for &mut (id, ref mut task) in state.parked_senders.iter_mut() {
+            if id == self.id {
+                *task = task::park();
+                return true
+            }
+        }
+        false
+    }
+}
+
+impl<T> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, msg: T) -> StartSend<T, SendError<T>> {
+        let task = {
+            let mut state = self.inner.lock().unwrap();
+            if !state.is_open {
+                return Err(SendError(msg))
+            }
+            // If the sender is currently blocked, reject the message before
+            // doing any work.
+            if self.is_parked(&mut state) {
+                return Ok(AsyncSink::NotReady(msg))
+            }
+            state.messages.push_back(msg);
+            if state.messages.len() > state.buffer {
+                state.parked_senders.push_back((self.id, task::park()));
+            }
+            state.recv_tasks.pop_front()
+        };
+        if let Some((_, task)) = task {
+            task.unpark();
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        let mut state = self.inner.lock().unwrap();
+        state.num_senders += 1;
+        Sender { inner: self.inner.clone(), id: state.next_id() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            state.num_senders -= 1;
+            let id = self.id;
+            state.parked_senders.retain(|&(s, _)| s != id);
+            if state.num_senders > 0 {
+                return
+            }
+            state.take_all_tasks()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Closes the channel
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receivers to drain messages that are buffered.
+    pub fn close(&mut self) {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            state.is_open = false;
+            state.take_all_tasks()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        let (msg, task) = {
+            let mut state = self.inner.lock().unwrap();
+            match state.messages.pop_front() {
+                Some(msg) => (msg, state.parked_senders.pop_front()),
+                None => {
+                    if state.num_senders == 0 || !state.is_open {
+                        return Ok(Async::Ready(None))
+                    }
+                    let id = self.id;
+                    state.recv_tasks.retain(|&(r, _)| r != id);
+                    state.recv_tasks.push_back((id, task::park()));
+                    return Ok(Async::NotReady)
+                }
+            }
+        };
+        if let Some((_, task)) = task {
+            task.unpark();
+        }
+        Ok(Async::Ready(Some(msg)))
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        let mut state = self.inner.lock().unwrap();
+        state.num_receivers += 1;
+        Receiver { inner: self.inner.clone(), id: state.next_id() }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            state.num_receivers -= 1;
+            let id = self.id;
+            state.recv_tasks.retain(|&(r, _)| r != id);
+            if state.num_receivers == 0 {
+                state.is_open = false;
+                state.take_all_tasks()
+            } else if !state.messages.is_empty() {
+                // We may have been notified of a message we'll now never
+                // receive, so pass the notification on to another receiver.
+                state.recv_tasks.pop_front().map(|(_, t)| t).into_iter().collect()
+            } else {
+                Vec::new()
+            }
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sender").finish()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Receiver").finish()
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because the channel is closed")
+    }
+}
+
+impl<T> Error for SendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        "send failed because the channel is closed"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<Sender<u32>>();
+    _assert_send::<Receiver<u32>>();
+}
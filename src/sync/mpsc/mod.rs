@@ -9,6 +9,10 @@
 //! the channel is at capacity, then send will be rejected and the task will be
 //! notified when additional capacity is available.
 //!
+//! Channels created with `channel_with_overflow` can instead drop or reject
+//! messages sent while the channel is at capacity, in which case senders
+//! never park.
+//!
 //! # Disconnection
 //!
 //! When all `Sender` handles have been dropped, it is no longer possible to
@@ -67,9 +71,12 @@
 // happens-before semantics required for the acquire / release semantics used
 // by the queue structure.
 
+use std::prelude::v1::*;
+
 use std::any::Any;
 use std::error::Error;
 use std::fmt;
+use std::mem;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::SeqCst;
 use std::sync::{Arc, Mutex};
@@ -78,7 +85,7 @@ use std::usize;
 
 use sync::mpsc::queue::{Queue, PopResult};
 use task::{self, Task};
-use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+use {Async, AsyncSink, Future, Poll, StartSend, Sink, Stream};
 
 mod queue;
 
@@ -92,11 +99,14 @@ pub struct Sender<T> {
     // Handle to the task that is blocked on this sender. This handle is sent
     // to the receiver half in order to be notified when the sender becomes
     // unblocked.
-    sender_task: SenderTask,
+    sender_task: Arc<Mutex<SenderTask>>,
 
     // True if the sender might be blocked. This is an optimization to avoid
     // having to lock the mutex most of the time.
     maybe_parked: bool,
+
+    // Identifies the task registered by `poll_closed`
+    close_id: usize,
 }
 
 /// The transmission end of a channel which is used to send values.
@@ -104,6 +114,20 @@ pub struct Sender<T> {
 /// This is created by the `unbounded` method.
 pub struct UnboundedSender<T>(Sender<T>);
 
+/// A handle to the transmission end of a channel which doesn't keep the
+/// channel open.
+///
+/// This is created by the `Sender::downgrade` method.
+pub struct WeakSender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// A handle to the transmission end of an unbounded channel which doesn't
+/// keep the channel open.
+///
+/// This is created by the `UnboundedSender::downgrade` method.
+pub struct WeakUnboundedSender<T>(WeakSender<T>);
+
 /// The receiving end of a channel which implements the `Stream` trait.
 ///
 /// This is a concrete implementation of a stream which can be used to represent
@@ -124,6 +148,50 @@ pub struct UnboundedReceiver<T>(Receiver<T>);
 /// dropped
 pub struct SendError<T>(T);
 
+/// Error type returned from `try_send`, saying why a message couldn't be sent
+/// and handing it back.
+pub enum TrySendError<T> {
+    /// The channel is at capacity, so the message could only be sent after
+    /// the receiver has made progress.
+    Full(T),
+
+    /// The receiving end of the channel has been closed or dropped, so the
+    /// message can never be sent.
+    Disconnected(T),
+}
+
+/// Error type returned from `try_recv`, saying why no message was received.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TryRecvError {
+    /// There are no messages in the channel right now, but more may be sent.
+    Empty,
+
+    /// There are no messages in the channel, and all senders have been
+    /// dropped or the receiver has been closed, so no more will be sent.
+    Disconnected,
+}
+
+/// What a bounded channel does with a message sent while it's at capacity.
+///
+/// This is chosen when creating a channel with `channel_with_overflow`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    /// Park the sender until the receiver has made progress. This is the
+    /// behavior of channels created with `channel`.
+    Backpressure,
+
+    /// Drop the message being sent, keeping the messages already in the
+    /// channel.
+    DropNewest,
+
+    /// Drop the oldest message in the channel to make room for the message
+    /// being sent, so the channel always holds the most recent messages.
+    DropOldest,
+
+    /// Reject the message being sent with an error.
+    Error,
+}
+
 impl<T> fmt::Debug for SendError<T> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_tuple("SendError")
@@ -153,10 +221,105 @@ impl<T> SendError<T> {
     }
 }
 
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TryRecvError::Empty => {
+                write!(fmt, "receive failed because channel is empty")
+            }
+            TryRecvError::Disconnected => {
+                write!(fmt, "receive failed because all senders are gone")
+            }
+        }
+    }
+}
+
+impl Error for TryRecvError {
+    fn description(&self) -> &str {
+        match *self {
+            TryRecvError::Empty => "receive failed because channel is empty",
+            TryRecvError::Disconnected => {
+                "receive failed because all senders are gone"
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            TrySendError::Full(_) => "Full",
+            TrySendError::Disconnected(_) => "Disconnected",
+        };
+        fmt.debug_tuple(name)
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrySendError::Full(_) => {
+                write!(fmt, "send failed because channel is full")
+            }
+            TrySendError::Disconnected(_) => {
+                write!(fmt, "send failed because receiver is gone")
+            }
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        match *self {
+            TrySendError::Full(_) => "send failed because channel is full",
+            TrySendError::Disconnected(_) => "send failed because receiver is gone",
+        }
+    }
+}
+
+impl<T> TrySendError<T> {
+    /// Returns true if this error is a result of the channel being full.
+    pub fn is_full(&self) -> bool {
+        match *self {
+            TrySendError::Full(_) => true,
+            TrySendError::Disconnected(_) => false,
+        }
+    }
+
+    /// Returns true if this error is a result of the receiver being gone.
+    pub fn is_disconnected(&self) -> bool {
+        !self.is_full()
+    }
+
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(msg) |
+            TrySendError::Disconnected(msg) => msg,
+        }
+    }
+}
+
 struct Inner<T> {
     // Max buffer size of the channel. If `None` then the channel is unbounded.
     buffer: Option<usize>,
 
+    // What happens to messages sent while the channel is at capacity
+    overflow: Overflow,
+
+    // Number of messages dropped because the channel was at capacity
+    num_dropped: AtomicUsize,
+
+    // With `Overflow::DropOldest`, senders need to pop messages off of the
+    // message queue as well as the receiver. As the queue only supports a
+    // single consumer, all pushes and pops (along with updates of the message
+    // count) happen while holding this lock in that mode.
+    pop_lock: Mutex<()>,
+
     // Internal channel state. Consists of the number of messages stored in the
     // channel as well as a flag signalling that the channel is closed.
     state: AtomicUsize,
@@ -165,13 +328,20 @@ struct Inner<T> {
     message_queue: Queue<Option<T>>,
 
     // Atomic, FIFO queue used to send parked task handles to the receiver.
-    parked_queue: Queue<SenderTask>,
+    parked_queue: Queue<Arc<Mutex<SenderTask>>>,
 
     // Number of senders in existence
     num_senders: AtomicUsize,
 
     // Handle to the receiver's task.
     recv_task: Mutex<ReceiverTask>,
+
+    // Tasks waiting for the channel to be closed through `poll_closed` or a
+    // `Closed` future, keyed by the id of the sender or future
+    close_tasks: Mutex<Vec<(usize, Task)>>,
+
+    // Used to hand out the ids keying `close_tasks`
+    next_close_id: AtomicUsize,
 }
 
 // Struct representation of `Inner::state`.
@@ -184,6 +354,31 @@ struct State {
     num_messages: usize,
 }
 
+// Sent to the consumer to wake up blocked producers
+struct SenderTask {
+    task: Option<Task>,
+
+    // `true` while the sender's handle is on the parked task queue. This is
+    // tracked separately from `task`, as a sender parked outside of a task
+    // context has no task to notify.
+    is_parked: bool,
+}
+
+impl SenderTask {
+    fn new() -> SenderTask {
+        SenderTask {
+            task: None,
+            is_parked: false,
+        }
+    }
+
+    // Marks the sender as unparked, returning the task to notify
+    fn unpark(&mut self) -> Option<Task> {
+        self.is_parked = false;
+        self.task.take()
+    }
+}
+
 struct ReceiverTask {
     unparked: bool,
     task: Option<Task>,
@@ -210,9 +405,6 @@ const MAX_CAPACITY: usize = !(OPEN_MASK);
 // a channel. This is because each sender gets a guaranteed slot.
 const MAX_BUFFER: usize = MAX_CAPACITY >> 1;
 
-// Sent to the consumer to wake up blocked producers
-type SenderTask = Arc<Mutex<Option<Task>>>;
-
 /// Creates an in-memory channel implementation of the `Stream` trait with
 /// bounded capacity.
 ///
@@ -227,10 +419,54 @@ type SenderTask = Arc<Mutex<Option<Task>>>;
 /// The `Receiver` returned implements the `Stream` trait and has access to any
 /// number of the associated combinators for transforming the result.
 pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_overflow(buffer, Overflow::Backpressure)
+}
+
+/// Creates an in-memory channel implementation of the `Stream` trait with
+/// bounded capacity, handling messages sent while it's at capacity according
+/// to `overflow`.
+///
+/// With `Overflow::Backpressure` this is the same as `channel`. With any of
+/// the other policies senders never park, and the channel capacity is exactly
+/// `buffer`: a message sent while the channel holds `buffer` messages is
+/// dropped or rejected instead. The number of messages dropped is available
+/// through `Sender::dropped_count` and `Receiver::dropped_count`.
+///
+/// With `Overflow::Error` a message is rejected with a `TrySendError::Full`
+/// from `try_send`. As `Sink::start_send` can only fail with a `SendError`,
+/// it fails with one in that case as well, so use `try_send` to tell a full
+/// channel apart from a disconnected one.
+///
+/// # Panics
+///
+/// This function panics if `buffer` is zero and `overflow` isn't
+/// `Overflow::Backpressure`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Stream;
+/// use futures::sync::mpsc::{self, Overflow};
+///
+/// let (mut tx, rx) = mpsc::channel_with_overflow(2, Overflow::DropOldest);
+/// for i in 0..5 {
+///     tx.try_send(i).unwrap();
+/// }
+/// drop(tx);
+///
+/// let mut rx = rx.wait();
+/// assert_eq!(rx.next(), Some(Ok(3)));
+/// assert_eq!(rx.next(), Some(Ok(4)));
+/// assert_eq!(rx.next(), None);
+/// ```
+pub fn channel_with_overflow<T>(buffer: usize, overflow: Overflow)
+                                -> (Sender<T>, Receiver<T>) {
     // Check that the requested buffer size does not exceed the maximum buffer
     // size permitted by the system.
     assert!(buffer < MAX_BUFFER, "requested buffer size too large");
-    channel2(Some(buffer))
+    assert!(buffer > 0 || overflow == Overflow::Backpressure,
+            "buffer must be non-zero unless applying backpressure");
+    channel2(Some(buffer), overflow)
 }
 
 /// Creates an in-memory channel implementation of the `Stream` trait with
@@ -246,13 +482,17 @@ pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
 /// the channel. Using an `unbounded` channel has the ability of causing the
 /// process to run out of memory. In this case, the process will be aborted.
 pub fn unbounded<T>() -> (UnboundedSender<T>, UnboundedReceiver<T>) {
-    let (tx, rx) = channel2(None);
+    let (tx, rx) = channel2(None, Overflow::Backpressure);
     (UnboundedSender(tx), UnboundedReceiver(rx))
 }
 
-fn channel2<T>(buffer: Option<usize>) -> (Sender<T>, Receiver<T>) {
+fn channel2<T>(buffer: Option<usize>, overflow: Overflow)
+               -> (Sender<T>, Receiver<T>) {
     let inner = Arc::new(Inner {
         buffer: buffer,
+        overflow: overflow,
+        num_dropped: AtomicUsize::new(0),
+        pop_lock: Mutex::new(()),
         state: AtomicUsize::new(INIT_STATE),
         message_queue: Queue::new(),
         parked_queue: Queue::new(),
@@ -261,13 +501,11 @@ fn channel2<T>(buffer: Option<usize>) -> (Sender<T>, Receiver<T>) {
             unparked: false,
             task: None,
         }),
+        close_tasks: Mutex::new(Vec::new()),
+        next_close_id: AtomicUsize::new(0),
     });
 
-    let tx = Sender {
-        inner: inner.clone(),
-        sender_task: Arc::new(Mutex::new(None)),
-        maybe_parked: false,
-    };
+    let tx = Sender::from_inner(inner.clone());
 
     let rx = Receiver {
         inner: inner,
@@ -283,6 +521,228 @@ fn channel2<T>(buffer: Option<usize>) -> (Sender<T>, Receiver<T>) {
  */
 
 impl<T> Sender<T> {
+    fn from_inner(inner: Arc<Inner<T>>) -> Sender<T> {
+        Sender {
+            close_id: inner.next_close_id.fetch_add(1, SeqCst),
+            inner: inner,
+            sender_task: Arc::new(Mutex::new(SenderTask::new())),
+            maybe_parked: false,
+        }
+    }
+
+    /// Polls this `Sender` to detect whether the channel has been closed.
+    ///
+    /// This returns `Ready` once the `Receiver` has been dropped or `close`d,
+    /// after which nothing sent on this channel will ever be received, so
+    /// producers can stop doing work as soon as nobody is listening. If
+    /// `NotReady` is returned the current task is scheduled to be notified
+    /// when the channel is closed.
+    ///
+    /// Like `Future::poll`, this function will panic if it's not called from
+    /// within the context of a task.
+    pub fn poll_closed(&mut self) -> Poll<(), ()> {
+        Ok(self.inner.poll_closed(self.close_id))
+    }
+
+    /// Returns a future which resolves once the channel has been closed.
+    ///
+    /// This is the future version of `poll_closed`. The returned future
+    /// doesn't count as a sender, so it doesn't keep the channel open: it
+    /// also resolves once all senders have been dropped.
+    pub fn closed(&self) -> Closed<T> {
+        Closed {
+            inner: self.inner.clone(),
+            id: self.inner.next_close_id.fetch_add(1, SeqCst),
+        }
+    }
+
+    /// Creates a `WeakSender` for this channel, which doesn't keep the
+    /// channel open but can be upgraded to a `Sender` while it is.
+    ///
+    /// Unlike `Sender` handles, `WeakSender` handles aren't counted as
+    /// senders, so the receiver will see the end of the stream once all
+    /// `Sender` handles have been dropped even if weak ones remain.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender { inner: self.inner.clone() }
+    }
+
+    /// Attempts to send a message on this `Sender` without blocking.
+    ///
+    /// This function, unlike `start_send`, is safe to call whether it's
+    /// called from the context of a task or not. Note that this function,
+    /// unlike `start_send`, will never register the current task to be
+    /// notified when the channel has capacity again.
+    ///
+    /// The message is handed back in a `TrySendError::Full` if the channel is
+    /// at capacity, and in a `TrySendError::Disconnected` if the receiver has
+    /// been closed or dropped.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        if self.inner.overflow != Overflow::Backpressure {
+            return self.do_send_overflow(msg);
+        }
+
+        if !decode_state(self.inner.state.load(SeqCst)).is_open {
+            return Err(TrySendError::Disconnected(msg));
+        }
+
+        // If the sender is currently blocked, reject the message
+        if !self.poll_unparked(false).is_ready() {
+            return Err(TrySendError::Full(msg));
+        }
+
+        // The channel has capacity to accept the message, so send it. If this
+        // fills up the channel the sender is parked without a task, and the
+        // next `start_send` or `poll_ready` will register one.
+        self.do_send(Some(msg), false)
+            .map_err(|SendError(msg)| TrySendError::Disconnected(msg))
+    }
+
+    /// Polls whether this sender can send a message right away.
+    ///
+    /// For channels created with `channel`, or with `Overflow::Backpressure`,
+    /// this returns `Ready` if the next message sent through `start_send` or
+    /// `try_send` will be accepted, which makes it possible to only produce a
+    /// message once there's room for it. Otherwise the current task is
+    /// registered to be notified once there is.
+    ///
+    /// Senders on channels with any other overflow policy never wait for
+    /// capacity, so this always returns `Ready` for them while the receiver
+    /// is around. No room is reserved in that case: if the channel is at
+    /// capacity when the next message is sent, `Overflow::Error` rejects it
+    /// and `Overflow::DropNewest` drops it, while `Overflow::DropOldest`
+    /// always accepts it.
+    ///
+    /// An error is returned if the receiver has been closed or dropped.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if called from outside the context of a task
+    /// while the channel is at capacity.
+    pub fn poll_ready(&mut self) -> Poll<(), SendError<()>> {
+        if !decode_state(self.inner.state.load(SeqCst)).is_open {
+            return Err(SendError(()));
+        }
+
+        Ok(self.poll_unparked(true))
+    }
+
+    /// Returns the number of messages which have been dropped because the
+    /// channel was at capacity.
+    ///
+    /// This is always zero unless the channel was created with
+    /// `channel_with_overflow` and `Overflow::DropNewest` or
+    /// `Overflow::DropOldest`.
+    pub fn dropped_count(&self) -> usize {
+        self.inner.num_dropped.load(SeqCst)
+    }
+
+    /// Returns the number of messages currently in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages the channel can hold before it's at
+    /// capacity.
+    ///
+    /// With backpressure this is `buffer + num-senders`, as each sender gets a
+    /// guaranteed slot, while with any other overflow policy it's `buffer`.
+    /// Returns `None` if the channel is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity()
+    }
+
+    /// Returns whether the channel is closed, in which case no further
+    /// messages can be sent on it.
+    ///
+    /// This is the case once the receiver has been closed or dropped, or all
+    /// senders have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Returns the number of senders of the channel.
+    pub fn sender_count(&self) -> usize {
+        self.inner.num_senders.load(SeqCst)
+    }
+
+    // Send a message on a channel which doesn't apply backpressure, dropping
+    // or rejecting it according to the channel's overflow policy if the
+    // channel is at capacity.
+    fn do_send_overflow(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        let overflow = self.inner.overflow;
+        let _guard = if overflow == Overflow::DropOldest {
+            Some(self.inner.pop_lock.lock().unwrap())
+        } else {
+            None
+        };
+
+        match self.inc_num_messages_bounded() {
+            None => return Err(TrySendError::Disconnected(msg)),
+            Some(true) => {}
+            Some(false) => {
+                match overflow {
+                    Overflow::DropNewest => {
+                        self.inner.num_dropped.fetch_add(1, SeqCst);
+                        return Ok(());
+                    }
+                    Overflow::Error => return Err(TrySendError::Full(msg)),
+                    Overflow::DropOldest => {
+                        // We hold the pop lock, so the queue holds exactly as
+                        // many messages as the count says, and the oldest
+                        // one can be replaced without changing the count.
+                        loop {
+                            match unsafe { self.inner.message_queue.pop() } {
+                                PopResult::Data(_) => break,
+                                PopResult::Empty => break,
+                                PopResult::Inconsistent => thread::yield_now(),
+                            }
+                        }
+                        self.inner.num_dropped.fetch_add(1, SeqCst);
+                    }
+                    Overflow::Backpressure => unreachable!(),
+                }
+            }
+        }
+
+        self.inner.message_queue.push(Some(msg));
+        drop(_guard);
+        self.signal();
+        Ok(())
+    }
+
+    // Increment the number of queued messages unless the channel is at
+    // capacity. Returns whether the count was incremented, or `None` if the
+    // channel is closed.
+    fn inc_num_messages_bounded(&self) -> Option<bool> {
+        let buffer = self.inner.buffer.expect("overflow policy on unbounded channel");
+        let mut curr = self.inner.state.load(SeqCst);
+
+        loop {
+            let mut state = decode_state(curr);
+
+            if !state.is_open {
+                return None;
+            }
+
+            if state.num_messages >= buffer {
+                return Some(false);
+            }
+
+            state.num_messages += 1;
+
+            let next = encode_state(&state);
+            match self.inner.state.compare_exchange(curr, next, SeqCst, SeqCst) {
+                Ok(_) => return Some(true),
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+
     // Do the send without failing
     fn do_send(&mut self, msg: Option<T>, can_park: bool) -> Result<(), SendError<T>> {
         // First, increment the number of messages contained by the channel.
@@ -351,11 +811,13 @@ impl<T> Sender<T> {
             assert!(state.num_messages < MAX_CAPACITY, "buffer space exhausted; \
                     sending this messages would overflow the state");
 
-            state.num_messages += 1;
-
-            // The channel is closed by all sender handles being dropped.
+            // The channel is closed by all sender handles being dropped. The
+            // termination message (None) isn't counted, so that the count is
+            // the number of actual messages in the channel.
             if close {
                 state.is_open = false;
+            } else {
+                state.num_messages += 1;
             }
 
             let next = encode_state(&state);
@@ -413,7 +875,11 @@ impl<T> Sender<T> {
             None
         };
 
-        *self.sender_task.lock().unwrap() = task;
+        {
+            let mut sender_task = self.sender_task.lock().unwrap();
+            sender_task.task = task;
+            sender_task.is_parked = true;
+        }
 
         // Send handle over queue
         let t = self.sender_task.clone();
@@ -425,14 +891,14 @@ impl<T> Sender<T> {
         self.maybe_parked = state.is_open;
     }
 
-    fn poll_unparked(&mut self) -> Async<()> {
+    fn poll_unparked(&mut self, do_park: bool) -> Async<()> {
         // First check the `maybe_parked` variable. This avoids acquiring the
         // lock in most cases
         if self.maybe_parked {
             // Get a lock on the task handle
             let mut task = self.sender_task.lock().unwrap();
 
-            if task.is_none() {
+            if !task.is_parked {
                 self.maybe_parked = false;
                 return Async::Ready(())
             }
@@ -443,7 +909,9 @@ impl<T> Sender<T> {
             //
             // Update the task in case the `Sender` has been moved to another
             // task
-            *task = Some(task::park());
+            if do_park {
+                task.task = Some(task::park());
+            }
 
             Async::NotReady
         } else {
@@ -457,9 +925,16 @@ impl<T> Sink for Sender<T> {
     type SinkError = SendError<T>;
 
     fn start_send(&mut self, msg: T) -> StartSend<T, SendError<T>> {
+        if self.inner.overflow != Overflow::Backpressure {
+            return match self.do_send_overflow(msg) {
+                Ok(()) => Ok(AsyncSink::Ready),
+                Err(e) => Err(SendError(e.into_inner())),
+            };
+        }
+
         // If the sender is currently blocked, reject the message before doing
         // any work.
-        if !self.poll_unparked().is_ready() {
+        if !self.poll_unparked(true).is_ready() {
             return Ok(AsyncSink::NotReady(msg));
         }
 
@@ -475,6 +950,28 @@ impl<T> Sink for Sender<T> {
 }
 
 impl<T> UnboundedSender<T> {
+    /// Polls this `UnboundedSender` to detect whether the channel has been
+    /// closed.
+    ///
+    /// See `Sender::poll_closed` for more details.
+    pub fn poll_closed(&mut self) -> Poll<(), ()> {
+        self.0.poll_closed()
+    }
+
+    /// Returns a future which resolves once the channel has been closed.
+    ///
+    /// See `Sender::closed` for more details.
+    pub fn closed(&self) -> Closed<T> {
+        self.0.closed()
+    }
+
+    /// Creates a `WeakUnboundedSender` for this channel, which doesn't keep
+    /// the channel open but can be upgraded to an `UnboundedSender` while it
+    /// is.
+    pub fn downgrade(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender(self.0.downgrade())
+    }
+
     /// Sends the provided message along this channel.
     ///
     /// This is an unbounded sender, so this function differs from `Sink::send`
@@ -487,6 +984,33 @@ impl<T> UnboundedSender<T> {
             Err(e) => Err(e),
         }
     }
+
+    /// Returns the number of messages currently in the channel.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of messages the channel can hold, which is always
+    /// `None` as the channel is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.0.capacity()
+    }
+
+    /// Returns whether the channel is closed, in which case no further
+    /// messages can be sent on it.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    /// Returns the number of senders of the channel.
+    pub fn sender_count(&self) -> usize {
+        self.0.sender_count()
+    }
 }
 
 impl<T> Sink for UnboundedSender<T> {
@@ -511,34 +1035,47 @@ impl<T> Clone for UnboundedSender<T> {
 
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Sender<T> {
-        // Since this atomic op isn't actually guarding any memory and we don't
-        // care about any orderings besides the ordering on the single atomic
-        // variable, a relaxed ordering is acceptable.
-        let mut curr = self.inner.num_senders.load(SeqCst);
-
-        loop {
-            // If the maximum number of senders has been reached, then fail
-            if curr == self.inner.max_senders() {
-                panic!("cannot clone `Sender` -- too many outstanding senders");
-            }
+        // As this sender exists the count of senders can't have dropped to
+        // zero, so this always succeeds.
+        self.inner.inc_num_senders();
+        Sender::from_inner(self.inner.clone())
+    }
+}
 
-            debug_assert!(curr < self.inner.max_senders());
+impl<T> WeakSender<T> {
+    /// Attempts to upgrade this `WeakSender` into a `Sender`.
+    ///
+    /// This returns `None` once all `Sender` handles of the channel have been
+    /// dropped, as the channel has then been closed by its senders.
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        if self.inner.inc_num_senders() {
+            Some(Sender::from_inner(self.inner.clone()))
+        } else {
+            None
+        }
+    }
+}
 
-            let next = curr + 1;
-            let actual = self.inner.num_senders.compare_and_swap(curr, next, SeqCst);
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> WeakSender<T> {
+        WeakSender { inner: self.inner.clone() }
+    }
+}
 
-            // The ABA problem doesn't matter here. We only care that the
-            // number of senders never exceeds the maximum.
-            if actual == curr {
-                return Sender {
-                    inner: self.inner.clone(),
-                    sender_task: Arc::new(Mutex::new(None)),
-                    maybe_parked: false,
-                };
-            }
+impl<T> WeakUnboundedSender<T> {
+    /// Attempts to upgrade this `WeakUnboundedSender` into an
+    /// `UnboundedSender`.
+    ///
+    /// This returns `None` once all `UnboundedSender` handles of the channel
+    /// have been dropped, as the channel has then been closed by its senders.
+    pub fn upgrade(&self) -> Option<UnboundedSender<T>> {
+        self.0.upgrade().map(UnboundedSender)
+    }
+}
 
-            curr = actual;
-        }
+impl<T> Clone for WeakUnboundedSender<T> {
+    fn clone(&self) -> WeakUnboundedSender<T> {
+        WeakUnboundedSender(self.0.clone())
     }
 }
 
@@ -549,10 +1086,39 @@ impl<T> Drop for Sender<T> {
 
         if prev == 1 {
             let _ = self.do_send(None, false);
+            // The channel is now closed, which `Closed` futures wait for
+            self.inner.unpark_close_tasks();
+        } else {
+            self.inner.cancel_closed(self.close_id);
         }
     }
 }
 
+/// A future which resolves once a channel has been closed.
+///
+/// This is created by the `Sender::closed` and `UnboundedSender::closed`
+/// methods.
+#[must_use = "futures do nothing unless polled"]
+pub struct Closed<T> {
+    inner: Arc<Inner<T>>,
+    id: usize,
+}
+
+impl<T> Future for Closed<T> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        Ok(self.inner.poll_closed(self.id))
+    }
+}
+
+impl<T> Drop for Closed<T> {
+    fn drop(&mut self) {
+        self.inner.cancel_closed(self.id);
+    }
+}
+
 /*
  *
  * ===== impl Receiver =====
@@ -560,6 +1126,129 @@ impl<T> Drop for Sender<T> {
  */
 
 impl<T> Receiver<T> {
+    /// Returns the number of messages which have been dropped because the
+    /// channel was at capacity.
+    ///
+    /// This is always zero unless the channel was created with
+    /// `channel_with_overflow` and `Overflow::DropNewest` or
+    /// `Overflow::DropOldest`.
+    pub fn dropped_count(&self) -> usize {
+        self.inner.num_dropped.load(SeqCst)
+    }
+
+    /// Attempts to receive a message without blocking.
+    ///
+    /// This function, unlike `Stream::poll`, is safe to call whether it's
+    /// called from the context of a task or not, and never registers the
+    /// current task to be notified of new messages.
+    ///
+    /// `TryRecvError::Empty` is returned if there are no messages in the
+    /// channel right now, and `TryRecvError::Disconnected` if no more will
+    /// ever arrive.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.next_message() {
+            Async::Ready(Some(msg)) => {
+                self.unpark_one();
+                if self.inner.overflow != Overflow::DropOldest {
+                    self.dec_num_messages(1);
+                }
+                Ok(msg)
+            }
+            Async::Ready(None) => Err(TryRecvError::Disconnected),
+            Async::NotReady => {
+                let state = decode_state(self.inner.state.load(SeqCst));
+                if !state.is_open && state.num_messages == 0 {
+                    Err(TryRecvError::Disconnected)
+                } else {
+                    Err(TryRecvError::Empty)
+                }
+            }
+        }
+    }
+
+    /// Receives up to `max` messages at once, appending them to `buf`.
+    ///
+    /// This is like calling `Stream::poll` repeatedly, except that the
+    /// bookkeeping for the messages taken out of the channel, including
+    /// unparking senders blocked on the channel's capacity, is done once for
+    /// the whole batch.
+    ///
+    /// Returns `Ready(n)` with the number of messages received if there were
+    /// any, and `Ready(0)` once the stream has terminated. If the channel is
+    /// empty, `NotReady` is returned and the current task is scheduled to be
+    /// notified when a message is sent, just like `Stream::poll`.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `max` is zero, and, like `Stream::poll`, when
+    /// called from outside the context of a task while the channel is empty.
+    pub fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> Poll<usize, ()> {
+        assert!(max > 0, "must receive at least one message");
+
+        let mut n = 0;
+        while n < max {
+            match self.next_message() {
+                Async::Ready(Some(msg)) => {
+                    buf.push(msg);
+                    n += 1;
+                }
+                Async::Ready(None) | Async::NotReady => break,
+            }
+        }
+
+        if n == 0 {
+            // Fall back to `poll`, which takes care of parking the task or
+            // detecting the end of the stream.
+            return match try_ready!(self.poll()) {
+                Some(msg) => {
+                    buf.push(msg);
+                    Ok(Async::Ready(1))
+                }
+                None => Ok(Async::Ready(0)),
+            };
+        }
+
+        self.unpark_many(n);
+        if self.inner.overflow != Overflow::DropOldest {
+            self.dec_num_messages(n);
+        }
+        Ok(Async::Ready(n))
+    }
+
+    /// Returns the number of messages currently in the channel.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages the channel can hold before it's at
+    /// capacity.
+    ///
+    /// With backpressure this is `buffer + num-senders`, as each sender gets a
+    /// guaranteed slot, while with any other overflow policy it's `buffer`.
+    /// Returns `None` if the channel is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.inner.capacity()
+    }
+
+    /// Returns whether the channel is closed, in which case no further
+    /// messages can be sent on it.
+    ///
+    /// This is the case once the receiver has been closed or dropped, or all
+    /// senders have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Returns the number of senders of the channel.
+    pub fn sender_count(&self) -> usize {
+        self.inner.num_senders.load(SeqCst)
+    }
+
     /// Closes the receiving half
     ///
     /// This prevents any further messages from being sent on the channel while
@@ -588,7 +1277,7 @@ impl<T> Receiver<T> {
         loop {
             match unsafe { self.inner.parked_queue.pop() } {
                 PopResult::Data(task) => {
-                    let task = task.lock().unwrap().take();
+                    let task = task.lock().unwrap().unpark();
                     if let Some(task) = task {
                         task.unpark();
                     }
@@ -597,9 +1286,26 @@ impl<T> Receiver<T> {
                 PopResult::Inconsistent => thread::yield_now(),
             }
         }
+
+        self.inner.unpark_close_tasks();
     }
 
     fn next_message(&mut self) -> Async<Option<T>> {
+        // Senders may pop messages as well, so pop the message and update the
+        // message count while holding the pop lock. See `Inner::pop_lock`.
+        if self.inner.overflow == Overflow::DropOldest {
+            let _guard = self.inner.pop_lock.lock().unwrap();
+            let msg = self.pop_message();
+            if let Async::Ready(Some(_)) = msg {
+                self.dec_num_messages(1);
+            }
+            return msg;
+        }
+
+        self.pop_message()
+    }
+
+    fn pop_message(&self) -> Async<Option<T>> {
         // Pop off a message
         loop {
             match unsafe { self.inner.message_queue.pop() } {
@@ -635,7 +1341,7 @@ impl<T> Receiver<T> {
                 PopResult::Data(task) => {
                     // Do this step first so that the lock is dropped when
                     // `unpark` is called
-                    let task = task.lock().unwrap().take();
+                    let task = task.lock().unwrap().unpark();
 
                     if let Some(task) = task {
                         task.unpark();
@@ -655,6 +1361,25 @@ impl<T> Receiver<T> {
         }
     }
 
+    // Unpark up to `n` task handles pending in the parked queue, taking them
+    // all off of the queue before unparking any of them
+    fn unpark_many(&mut self, n: usize) {
+        let mut tasks = Vec::new();
+        while tasks.len() < n {
+            match unsafe { self.inner.parked_queue.pop() } {
+                PopResult::Data(task) => tasks.push(task.lock().unwrap().unpark()),
+                PopResult::Empty => break,
+                PopResult::Inconsistent => thread::yield_now(),
+            }
+        }
+
+        for task in tasks {
+            if let Some(task) = task {
+                task.unpark();
+            }
+        }
+    }
+
     // Try to park the receiver task
     fn try_park(&self) -> TryPark {
         let curr = self.inner.state.load(SeqCst);
@@ -678,13 +1403,13 @@ impl<T> Receiver<T> {
         TryPark::Parked
     }
 
-    fn dec_num_messages(&self) {
+    fn dec_num_messages(&self, n: usize) {
         let mut curr = self.inner.state.load(SeqCst);
 
         loop {
             let mut state = decode_state(curr);
 
-            state.num_messages -= 1;
+            state.num_messages -= n;
 
             let next = encode_state(&state);
             match self.inner.state.compare_exchange(curr, next, SeqCst, SeqCst) {
@@ -733,8 +1458,11 @@ impl<T> Stream for Receiver<T> {
             // one and unpark it.
             self.unpark_one();
 
-            // Decrement number of messages
-            self.dec_num_messages();
+            // Decrement number of messages, unless `next_message` already
+            // did so or this is the termination message which isn't counted
+            if msg.is_some() && self.inner.overflow != Overflow::DropOldest {
+                self.dec_num_messages(1);
+            }
 
             // Return the message
             return Ok(Async::Ready(msg));
@@ -760,6 +1488,47 @@ impl<T> UnboundedReceiver<T> {
     pub fn close(&mut self) {
         self.0.close();
     }
+
+    /// Attempts to receive a message without blocking.
+    ///
+    /// See `Receiver::try_recv` for more details.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.0.try_recv()
+    }
+
+    /// Receives up to `max` messages at once, appending them to `buf`.
+    ///
+    /// See `Receiver::recv_many` for more details.
+    pub fn recv_many(&mut self, buf: &mut Vec<T>, max: usize) -> Poll<usize, ()> {
+        self.0.recv_many(buf, max)
+    }
+
+    /// Returns the number of messages currently in the channel.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no messages in the channel.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of messages the channel can hold, which is always
+    /// `None` as the channel is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.0.capacity()
+    }
+
+    /// Returns whether the channel is closed, in which case no further
+    /// messages can be sent on it.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+
+    /// Returns the number of senders of the channel.
+    pub fn sender_count(&self) -> usize {
+        self.0.sender_count()
+    }
 }
 
 impl<T> Stream for UnboundedReceiver<T> {
@@ -786,6 +1555,90 @@ impl<T> Inner<T> {
             None => MAX_BUFFER,
         }
     }
+
+    // Increments the number of senders, returning `false` instead if it has
+    // already dropped to zero, in which case the channel has been closed by
+    // its senders and mustn't be reopened.
+    fn inc_num_senders(&self) -> bool {
+        // Since this atomic op isn't actually guarding any memory and we don't
+        // care about any orderings besides the ordering on the single atomic
+        // variable, a relaxed ordering is acceptable.
+        let mut curr = self.num_senders.load(SeqCst);
+
+        loop {
+            if curr == 0 {
+                return false;
+            }
+
+            // If the maximum number of senders has been reached, then fail
+            if curr == self.max_senders() {
+                panic!("cannot clone `Sender` -- too many outstanding senders");
+            }
+
+            debug_assert!(curr < self.max_senders());
+
+            let next = curr + 1;
+
+            // The ABA problem doesn't matter here. We only care that the
+            // number of senders never exceeds the maximum.
+            match self.num_senders.compare_exchange(curr, next, SeqCst, SeqCst) {
+                Ok(_) => return true,
+                Err(actual) => curr = actual,
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        decode_state(self.state.load(SeqCst)).num_messages
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        self.buffer.map(|buffer| {
+            match self.overflow {
+                Overflow::Backpressure => buffer + self.num_senders.load(SeqCst),
+                _ => buffer,
+            }
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        !decode_state(self.state.load(SeqCst)).is_open
+    }
+
+    fn poll_closed(&self, id: usize) -> Async<()> {
+        if self.is_closed() {
+            return Async::Ready(());
+        }
+
+        {
+            let mut tasks = self.close_tasks.lock().unwrap();
+            let task = task::park();
+            match tasks.iter().position(|&(i, _)| i == id) {
+                Some(pos) => tasks[pos].1 = task,
+                None => tasks.push((id, task)),
+            }
+        }
+
+        // Check again in case the channel was closed while we were
+        // registering our task, as we might have missed the notification.
+        if self.is_closed() {
+            Async::Ready(())
+        } else {
+            Async::NotReady
+        }
+    }
+
+    // Removes the task registered for `id` by `poll_closed`, if any
+    fn cancel_closed(&self, id: usize) {
+        self.close_tasks.lock().unwrap().retain(|&(i, _)| i != id);
+    }
+
+    fn unpark_close_tasks(&self) {
+        let tasks = mem::replace(&mut *self.close_tasks.lock().unwrap(), Vec::new());
+        for (_, task) in tasks {
+            task.unpark();
+        }
+    }
 }
 
 unsafe impl<T: Send> Send for Inner<T> {}
@@ -0,0 +1,363 @@
+use std::prelude::v1::*;
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware mutual exclusion lock which can be shared between any
+/// number of owners.
+///
+/// Handles to a `Mutex` are cheap to clone, and all clones refer to the same
+/// lock. Unlike `std::sync::Mutex`, waiting for the lock never blocks the
+/// thread, but instead parks the current task until the lock is handed to it.
+///
+/// The lock is fair: tasks acquire it in the order in which they started
+/// waiting for it. When the lock is released it's handed directly to the
+/// longest waiting task, so a task which keeps locking and unlocking can't
+/// starve the others.
+///
+/// The lock can be acquired either through `lock`, which borrows the `Mutex`
+/// and resolves to a `MutexGuard`, or `lock_owned`, which consumes a handle
+/// and resolves to a `MutexAcquired` that can be carried across combinators
+/// such as `and_then`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::sync::Mutex;
+///
+/// let mutex = Mutex::new(0);
+/// let mutex2 = mutex.clone();
+///
+/// let done = mutex2.lock_owned().map(|mut n| {
+///     *n += 1;
+///     n.unlock()
+/// });
+/// let mutex2 = done.wait().unwrap();
+///
+/// assert_eq!(*mutex.lock().wait().unwrap(), 1);
+/// assert_eq!(*mutex2.try_lock().unwrap(), 1);
+/// ```
+pub struct Mutex<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    state: sync::Mutex<State>,
+    data: UnsafeCell<T>,
+}
+
+struct State {
+    locked: bool,
+    // Tasks waiting for the lock, in the order in which they'll acquire it
+    waiters: VecDeque<Waiter>,
+    // A waiter which the lock has been handed to, but which hasn't been
+    // polled since to take it
+    handoff: Option<usize>,
+    next_id: usize,
+}
+
+struct Waiter {
+    id: usize,
+    task: Task,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+// The lock only needs `T: Send` to be shared, but a shared guard hands out `&T`
+// to every thread it's shared with, so the guards need `T: Sync` as well. These
+// impls take the place of the ones that would otherwise be derived from
+// `Inner`.
+unsafe impl<'a, T: Send + Sync> Sync for MutexGuard<'a, T> {}
+unsafe impl<T: Send + Sync> Sync for MutexAcquired<T> {}
+
+impl<T> Mutex<T> {
+    /// Creates a new unlocked `Mutex` protecting the provided data.
+    pub fn new(t: T) -> Mutex<T> {
+        Mutex {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    locked: false,
+                    waiters: VecDeque::new(),
+                    handoff: None,
+                    next_id: 0,
+                }),
+                data: UnsafeCell::new(t),
+            }),
+        }
+    }
+
+    /// Attempts to acquire this lock immediately, returning `None` if it's
+    /// currently held or other tasks are already waiting for it.
+    ///
+    /// This function doesn't require a task context.
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.locked {
+            None
+        } else {
+            state.locked = true;
+            Some(MutexGuard { inner: &self.inner })
+        }
+    }
+
+    /// Returns a future which resolves to a guard once this lock has been
+    /// acquired.
+    ///
+    /// The current task is queued up behind any other tasks already waiting
+    /// for the lock when the returned future is first polled. If the future
+    /// is dropped before it resolves it gives up its place in the queue, and
+    /// if the lock had already been handed to it then the lock is passed on
+    /// to the next waiter.
+    ///
+    /// Note that the returned future will never resolve to an error.
+    pub fn lock(&self) -> MutexLock<T> {
+        MutexLock {
+            inner: &self.inner,
+            id: None,
+        }
+    }
+
+    /// Perform a "blocking lock" of this lock, consuming this lock handle and
+    /// returning a future to the acquired lock.
+    ///
+    /// This is like `lock`, except that the returned future resolves to a
+    /// `MutexAcquired<T>` which owns its handle to the lock, and so can be
+    /// passed along to later steps of a computation. The original handle can
+    /// be recovered through `MutexAcquired::unlock`.
+    ///
+    /// Note that the returned future will never resolve to an error.
+    pub fn lock_owned(self) -> MutexAcquire<T> {
+        MutexAcquire {
+            inner: Some(self),
+            id: None,
+        }
+    }
+}
+
+impl<T> Clone for Mutex<T> {
+    fn clone(&self) -> Mutex<T> {
+        Mutex { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Inner<T> {
+    // Attempts to acquire the lock on behalf of the waiter `id`, queueing it
+    // up if it isn't already waiting.
+    fn poll_lock(&self, id: &mut Option<usize>) -> Async<()> {
+        let mut state = self.state.lock().unwrap();
+        match *id {
+            None => {
+                if !state.locked {
+                    state.locked = true;
+                    return Async::Ready(())
+                }
+                let me = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiters.push_back(Waiter { id: me, task: task::park() });
+                *id = Some(me);
+                Async::NotReady
+            }
+            Some(me) => {
+                if state.handoff == Some(me) {
+                    state.handoff = None;
+                    *id = None;
+                    return Async::Ready(())
+                }
+                // We're still in the queue, but may be running on a different
+                // task than the last time we were polled.
+                for waiter in state.waiters.iter_mut() {
+                    if waiter.id == me {
+                        waiter.task = task::park();
+                    }
+                }
+                Async::NotReady
+            }
+        }
+    }
+
+    // Called when a waiter gives up, removing it from the queue or passing on
+    // the lock if it had already been handed to it.
+    fn cancel(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        if state.handoff == Some(id) {
+            state.handoff = None;
+            let task = unlock(&mut state);
+            drop(state);
+            if let Some(task) = task {
+                task.unpark();
+            }
+        } else {
+            state.waiters.retain(|w| w.id != id);
+        }
+    }
+
+    fn unlock(&self) {
+        let task = unlock(&mut self.state.lock().unwrap());
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+// Releases the lock, handing it to the next waiter if there is one and
+// returning its task to be unparked once the state lock has been released.
+fn unlock(state: &mut State) -> Option<Task> {
+    match state.waiters.pop_front() {
+        Some(waiter) => {
+            state.handoff = Some(waiter.id);
+            Some(waiter.task)
+        }
+        None => {
+            state.locked = false;
+            None
+        }
+    }
+}
+
+/// Returned RAII guard from the `try_lock` method and `MutexLock` future.
+///
+/// This structure acts as a sentinel to the data in the `Mutex<T>` itself,
+/// implementing `Deref` and `DerefMut` to `T`. When dropped, the lock will be
+/// unlocked.
+pub struct MutexGuard<'a, T: 'a> {
+    inner: &'a Inner<T>,
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.data.get() }
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.inner.unlock();
+    }
+}
+
+/// Future returned by `Mutex::lock` which will resolve to a `MutexGuard` when
+/// the lock is acquired.
+#[must_use = "futures do nothing unless polled"]
+pub struct MutexLock<'a, T: 'a> {
+    inner: &'a Inner<T>,
+    id: Option<usize>,
+}
+
+impl<'a, T> Future for MutexLock<'a, T> {
+    type Item = MutexGuard<'a, T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<MutexGuard<'a, T>, ()> {
+        match self.inner.poll_lock(&mut self.id) {
+            Async::Ready(()) => Ok(MutexGuard { inner: self.inner }.into()),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<'a, T> Drop for MutexLock<'a, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.inner.cancel(id);
+        }
+    }
+}
+
+/// Future returned by `Mutex::lock_owned` which will resolve when the lock is
+/// acquired.
+#[must_use = "futures do nothing unless polled"]
+pub struct MutexAcquire<T> {
+    inner: Option<Mutex<T>>,
+    id: Option<usize>,
+}
+
+impl<T> Future for MutexAcquire<T> {
+    type Item = MutexAcquired<T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<MutexAcquired<T>, ()> {
+        let ready = {
+            let mutex = self.inner.as_ref().expect("cannot poll MutexAcquire twice");
+            mutex.inner.poll_lock(&mut self.id)
+        };
+        match ready {
+            Async::Ready(()) => {
+                Ok(MutexAcquired { inner: self.inner.take().unwrap() }.into())
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<T> Drop for MutexAcquire<T> {
+    fn drop(&mut self) {
+        if let (Some(ref mutex), Some(id)) = (self.inner.take(), self.id) {
+            mutex.inner.cancel(id);
+        }
+    }
+}
+
+/// Resolved value of the `MutexAcquire<T>` future.
+///
+/// This value, like `MutexGuard<T>`, is a sentinel to the value `T` through
+/// implementations of `Deref` and `DerefMut`. When dropped will unlock the
+/// lock, and the original `Mutex<T>` handle can be recovered through the
+/// `unlock` method.
+pub struct MutexAcquired<T> {
+    inner: Mutex<T>,
+}
+
+impl<T> MutexAcquired<T> {
+    /// Recovers the original `Mutex<T>`, unlocking this lock.
+    pub fn unlock(self) -> Mutex<T> {
+        let mutex = self.inner.clone();
+        drop(self);
+        mutex
+    }
+}
+
+impl<T> Deref for MutexAcquired<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.inner.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexAcquired<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.inner.data.get() }
+    }
+}
+
+impl<T> Drop for MutexAcquired<T> {
+    fn drop(&mut self) {
+        self.inner.inner.unlock();
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<Mutex<u32>>();
+    _assert_sync::<Mutex<u32>>();
+    _assert_send::<MutexAcquire<u32>>();
+    _assert_send::<MutexAcquired<u32>>();
+    _assert_sync::<MutexGuard<u32>>();
+    _assert_sync::<MutexAcquired<u32>>();
+    _assert_send::<Mutex<::std::cell::Cell<u32>>>();
+    _assert_sync::<Mutex<::std::cell::Cell<u32>>>();
+}
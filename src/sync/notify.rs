@@ -0,0 +1,248 @@
+use std::prelude::v1::*;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware primitive for notifying tasks of an event, similar to a
+/// condition variable.
+///
+/// Tasks wait for a notification through the `Notified` future returned by
+/// `notified`, and are woken up through `notify_one` or `notify_all`.
+///
+/// If `notify_one` is called while no task is waiting, a permit is stored
+/// instead, and the next `Notified` future to be polled completes right away
+/// by consuming it. This means a notification which is sent just before a
+/// task starts waiting for it isn't lost. At most one permit is stored, no
+/// matter how many times `notify_one` is called.
+///
+/// Handles to a `Notify` are cheap to clone, and all clones refer to the same
+/// set of waiters.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use futures::Future;
+/// use futures::sync::Notify;
+///
+/// let notify = Notify::new();
+/// let notify2 = notify.clone();
+///
+/// let t = thread::spawn(move || {
+///     notify2.notify_one();
+/// });
+///
+/// notify.notified().wait().unwrap();
+/// t.join().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Notify {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: sync::Mutex<State>,
+}
+
+struct State {
+    permit: bool,
+    // Tasks waiting for a notification, in the order `notify_one` wakes them
+    waiters: VecDeque<Waiter>,
+    // Waiters which have been notified, but which haven't been polled since
+    // to find out, along with whether it was by `notify_one`
+    notified: Vec<(usize, bool)>,
+    next_id: usize,
+}
+
+struct Waiter {
+    id: usize,
+    task: Task,
+}
+
+impl Notify {
+    /// Creates a new `Notify` with no waiters and no stored permit.
+    pub fn new() -> Notify {
+        Notify {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    permit: false,
+                    waiters: VecDeque::new(),
+                    notified: Vec::new(),
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Returns a future which resolves once this `Notify` is notified.
+    ///
+    /// The current task starts waiting when the returned future is first
+    /// polled, which consumes the stored permit instead if there is one.
+    ///
+    /// The returned future will never resolve to an error.
+    pub fn notified(&self) -> Notified {
+        Notified {
+            notify: self.clone(),
+            id: None,
+        }
+    }
+
+    /// Wakes up the task which has been waiting the longest, or stores a
+    /// permit for the next task to wait if none are.
+    pub fn notify_one(&self) {
+        let task = self.inner.state.lock().unwrap().notify_one();
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+
+    /// Wakes up all tasks which are currently waiting.
+    ///
+    /// Unlike `notify_one`, this doesn't store a permit if no tasks are
+    /// waiting, so tasks which start waiting after this call aren't affected
+    /// by it.
+    pub fn notify_all(&self) {
+        let waiters = {
+            let mut state = self.inner.state.lock().unwrap();
+            let waiters = mem::replace(&mut state.waiters, VecDeque::new());
+            for waiter in waiters.iter() {
+                state.notified.push((waiter.id, false));
+            }
+            waiters
+        };
+        for waiter in waiters {
+            waiter.task.unpark();
+        }
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Notify {
+        Notify::new()
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.inner.state.lock().unwrap();
+        fmt.debug_struct("Notify")
+           .field("permit", &state.permit)
+           .field("waiters", &state.waiters.len())
+           .finish()
+    }
+}
+
+impl State {
+    // Notifies the longest waiting waiter or stores a permit, returning the
+    // task to be unparked once the state lock has been released.
+    fn notify_one(&mut self) -> Option<Task> {
+        match self.waiters.pop_front() {
+            Some(waiter) => {
+                self.notified.push((waiter.id, true));
+                Some(waiter.task)
+            }
+            None => {
+                self.permit = true;
+                None
+            }
+        }
+    }
+}
+
+/// Future returned by `Notify::notified` which will resolve once the `Notify`
+/// is notified.
+#[must_use = "futures do nothing unless polled"]
+pub struct Notified {
+    notify: Notify,
+    id: Option<usize>,
+}
+
+impl Future for Notified {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut state = self.notify.inner.state.lock().unwrap();
+        match self.id {
+            None => {
+                if state.permit {
+                    state.permit = false;
+                    return Ok(Async::Ready(()))
+                }
+                let me = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiters.push_back(Waiter { id: me, task: task::park() });
+                self.id = Some(me);
+                Ok(Async::NotReady)
+            }
+            Some(me) => {
+                if let Some(pos) = state.notified.iter().position(|&(n, _)| n == me) {
+                    state.notified.swap_remove(pos);
+                    self.id = None;
+                    return Ok(Async::Ready(()))
+                }
+                // We're still waiting, so rather than registering again just
+                // make sure the right task gets woken up.
+                for waiter in state.waiters.iter_mut() {
+                    if waiter.id == me && !waiter.task.is_current() {
+                        waiter.task = task::park();
+                    }
+                }
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+impl Drop for Notified {
+    fn drop(&mut self) {
+        let me = match self.id {
+            Some(id) => id,
+            None => return,
+        };
+        let task = {
+            let mut state = self.notify.inner.state.lock().unwrap();
+            match state.notified.iter().position(|&(n, _)| n == me) {
+                // A notification from `notify_one` was meant to wake up one
+                // task which would act on it, so pass it on rather than
+                // losing it.
+                Some(pos) => {
+                    let (_, one) = state.notified.swap_remove(pos);
+                    if one {
+                        state.notify_one()
+                    } else {
+                        None
+                    }
+                }
+                None => {
+                    state.waiters.retain(|w| w.id != me);
+                    None
+                }
+            }
+        };
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+impl fmt::Debug for Notified {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Notified")
+           .field("waiting", &self.id.is_some())
+           .finish()
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<Notify>();
+    _assert_sync::<Notify>();
+    _assert_send::<Notified>();
+}
@@ -0,0 +1,380 @@
+use std::prelude::v1::*;
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::sync::{self, Arc};
+
+use {Async, Future, IntoFuture, Poll};
+use task::{self, Task};
+
+/// A futures-aware cell which is initialized at most once, by a future.
+///
+/// This is useful for values shared by many tasks which need to be built
+/// asynchronously, such as connection handles or configuration. The first
+/// task to call `get_or_init` runs its initializer, and every other task
+/// which calls it in the meantime waits for that initializer to finish
+/// rather than running its own.
+///
+/// If the initializer fails, the error is returned to the task which ran it
+/// and the cell is left empty, so the next task waiting in `get_or_init` gets
+/// to try again with its own initializer.
+///
+/// Handles to a `OnceCell` are cheap to clone, and all clones refer to the
+/// same cell.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::future;
+/// use futures::sync::OnceCell;
+///
+/// let cell = OnceCell::new();
+/// assert!(cell.get().is_none());
+///
+/// let value = cell.get_or_init(|| future::ok::<_, ()>(1)).wait().unwrap();
+/// assert_eq!(*value, 1);
+///
+/// // The initializer only ever runs once
+/// let value = cell.get_or_init(|| future::ok::<_, ()>(2)).wait().unwrap();
+/// assert_eq!(*value, 1);
+/// ```
+pub struct OnceCell<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    state: sync::Mutex<State>,
+    // Only written by the initializer while `state.init` is `Running`, and
+    // never written again once it's `Done`
+    value: UnsafeCell<Option<T>>,
+}
+
+struct State {
+    init: Init,
+    // Tasks waiting for another task's initializer to finish
+    waiters: Vec<(usize, Task)>,
+    next_id: usize,
+}
+
+#[derive(PartialEq)]
+enum Init {
+    Empty,
+    Running,
+    Done,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send + Sync> Sync for Inner<T> {}
+
+impl<T> OnceCell<T> {
+    /// Creates a new, empty cell.
+    pub fn new() -> OnceCell<T> {
+        OnceCell {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    init: Init::Empty,
+                    waiters: Vec::new(),
+                    next_id: 0,
+                }),
+                value: UnsafeCell::new(None),
+            }),
+        }
+    }
+
+    /// Returns a reference to the value in this cell, or `None` if it hasn't
+    /// been initialized yet.
+    ///
+    /// This function doesn't require a task context.
+    pub fn get(&self) -> Option<&T> {
+        if self.inner.state.lock().unwrap().init == Init::Done {
+            Some(self.inner.value())
+        } else {
+            None
+        }
+    }
+
+    /// Initializes this cell with `value`, unless it's already initialized or
+    /// being initialized, in which case `value` is handed back.
+    ///
+    /// Tasks waiting in `get_or_init` are woken up if this succeeds.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            if state.init != Init::Empty {
+                return Err(value)
+            }
+            state.init = Init::Running;
+        }
+        self.inner.finish(Some(value));
+        Ok(())
+    }
+
+    /// Returns a future which resolves to a reference to the value in this
+    /// cell, initializing it with the future returned by `f` if needed.
+    ///
+    /// When the returned future is polled and the cell is empty, `f` is
+    /// called and the future it returns is run to initialize the cell. If
+    /// another task is already initializing the cell, the returned future
+    /// waits for it instead and `f` isn't called.
+    ///
+    /// If the initializer resolves to an error, the returned future resolves
+    /// to that error and the cell is left empty. The next waiting task, if
+    /// any, then runs its own initializer. The cell is also left empty if the
+    /// returned future is dropped while it's initializing the cell, including
+    /// when `f` or the future it returns panics.
+    ///
+    /// The returned future borrows this handle, so it can't be used in a
+    /// future which must be `'static`, such as one spawned onto a thread pool.
+    /// `get_or_init_cloned` can be used there instead.
+    pub fn get_or_init<F, R>(&self, f: F) -> OnceCellGetOrInit<T, F, R>
+        where F: FnOnce() -> R,
+              R: IntoFuture<Item = T>,
+    {
+        OnceCellGetOrInit {
+            inner: &self.inner,
+            init: Initializer::new(f),
+        }
+    }
+
+    /// Returns a future which resolves to a clone of the value in this cell,
+    /// initializing it with the future returned by `f` if needed.
+    ///
+    /// This works just like `get_or_init`, except that the returned future
+    /// holds its own handle to the cell rather than borrowing this one.
+    pub fn get_or_init_cloned<F, R>(&self, f: F) -> OnceCellGetOrInitCloned<T, F, R>
+        where F: FnOnce() -> R,
+              R: IntoFuture<Item = T>,
+              T: Clone,
+    {
+        OnceCellGetOrInitCloned {
+            cell: self.clone(),
+            init: Initializer::new(f),
+        }
+    }
+}
+
+impl<T> Clone for OnceCell<T> {
+    fn clone(&self) -> OnceCell<T> {
+        OnceCell { inner: self.inner.clone() }
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> OnceCell<T> {
+        OnceCell::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("OnceCell")
+           .field("value", &self.get())
+           .finish()
+    }
+}
+
+impl<T> Inner<T> {
+    // Must only be called once `state.init` has been observed to be `Done`
+    fn value(&self) -> &T {
+        unsafe { (*self.value.get()).as_ref().unwrap() }
+    }
+
+    // Finishes a running initialization, storing `value` if it succeeded and
+    // waking up all waiters either way.
+    fn finish(&self, value: Option<T>) {
+        let tasks = {
+            let mut state = self.state.lock().unwrap();
+            assert!(state.init == Init::Running);
+            state.init = match value {
+                Some(value) => {
+                    unsafe { *self.value.get() = Some(value); }
+                    Init::Done
+                }
+                None => Init::Empty,
+            };
+            state.waiters.drain(..).map(|(_, task)| task).collect::<Vec<_>>()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+// The state shared by the futures returned from `get_or_init` and
+// `get_or_init_cloned`, which either wait for another task's initializer or
+// run their own.
+struct Initializer<F, R: IntoFuture> {
+    f: Option<F>,
+    // Set from when we mark the cell as `Running` until we've finished
+    // initializing it, so that a panic in `f` or `future` still lets someone
+    // else have a go at initializing it once we're dropped
+    running: bool,
+    future: Option<R::Future>,
+    id: Option<usize>,
+}
+
+impl<F, R> Initializer<F, R>
+    where F: FnOnce() -> R,
+          R: IntoFuture,
+{
+    fn new(f: F) -> Initializer<F, R> {
+        Initializer {
+            f: Some(f),
+            running: false,
+            future: None,
+            id: None,
+        }
+    }
+
+    // Resolves once `inner` has been initialized, by us or by someone else
+    fn poll(&mut self, inner: &Inner<R::Item>) -> Poll<(), R::Error> {
+        if !self.running {
+            let mut state = inner.state.lock().unwrap();
+            match state.init {
+                Init::Done => return Ok(Async::Ready(())),
+                Init::Running => {
+                    let task = task::park();
+                    match self.id {
+                        Some(id) => {
+                            match state.waiters.iter().position(|&(w, _)| w == id) {
+                                Some(pos) => state.waiters[pos].1 = task,
+                                None => state.waiters.push((id, task)),
+                            }
+                        }
+                        None => {
+                            let id = state.next_id;
+                            state.next_id = state.next_id.wrapping_add(1);
+                            state.waiters.push((id, task));
+                            self.id = Some(id);
+                        }
+                    }
+                    return Ok(Async::NotReady)
+                }
+                Init::Empty => {
+                    state.init = Init::Running;
+                    self.running = true;
+                    if let Some(id) = self.id.take() {
+                        state.waiters.retain(|&(w, _)| w != id);
+                    }
+                }
+            }
+        }
+
+        if self.future.is_none() {
+            let f = self.f.take().expect("cannot poll OnceCellGetOrInit twice");
+            self.future = Some(f().into_future());
+        }
+        let res = match self.future.as_mut().unwrap().poll() {
+            Ok(Async::NotReady) => return Ok(Async::NotReady),
+            Ok(Async::Ready(value)) => Ok(value),
+            Err(e) => Err(e),
+        };
+        self.future = None;
+        self.running = false;
+        match res {
+            Ok(value) => {
+                inner.finish(Some(value));
+                Ok(Async::Ready(()))
+            }
+            Err(e) => {
+                inner.finish(None);
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<F, R: IntoFuture> Initializer<F, R> {
+    // Called on drop, which isn't bounded on `F` returning `R`
+    fn cancel<T>(&mut self, inner: &Inner<T>) {
+        if self.running {
+            // Let someone else have a go at initializing the cell
+            self.future = None;
+            self.running = false;
+            inner.finish(None);
+        } else if let Some(id) = self.id.take() {
+            let mut state = inner.state.lock().unwrap();
+            state.waiters.retain(|&(w, _)| w != id);
+        }
+    }
+}
+
+/// Future returned by `OnceCell::get_or_init` which will resolve to a
+/// reference to the value in the cell once it has been initialized.
+#[must_use = "futures do nothing unless polled"]
+pub struct OnceCellGetOrInit<'a, T: 'a, F, R: IntoFuture> {
+    inner: &'a Inner<T>,
+    init: Initializer<F, R>,
+}
+
+impl<'a, T, F, R> Future for OnceCellGetOrInit<'a, T, F, R>
+    where F: FnOnce() -> R,
+          R: IntoFuture<Item = T>,
+{
+    type Item = &'a T;
+    type Error = R::Error;
+
+    fn poll(&mut self) -> Poll<&'a T, R::Error> {
+        try_ready!(self.init.poll(self.inner));
+        Ok(Async::Ready(self.inner.value()))
+    }
+}
+
+impl<'a, T, F, R: IntoFuture> Drop for OnceCellGetOrInit<'a, T, F, R> {
+    fn drop(&mut self) {
+        self.init.cancel(self.inner);
+    }
+}
+
+impl<'a, T, F, R: IntoFuture> fmt::Debug for OnceCellGetOrInit<'a, T, F, R> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("OnceCellGetOrInit")
+           .field("initializing", &self.init.running)
+           .finish()
+    }
+}
+
+/// Future returned by `OnceCell::get_or_init_cloned` which will resolve to a
+/// clone of the value in the cell once it has been initialized.
+#[must_use = "futures do nothing unless polled"]
+pub struct OnceCellGetOrInitCloned<T, F, R: IntoFuture> {
+    cell: OnceCell<T>,
+    init: Initializer<F, R>,
+}
+
+impl<T, F, R> Future for OnceCellGetOrInitCloned<T, F, R>
+    where F: FnOnce() -> R,
+          R: IntoFuture<Item = T>,
+          T: Clone,
+{
+    type Item = T;
+    type Error = R::Error;
+
+    fn poll(&mut self) -> Poll<T, R::Error> {
+        try_ready!(self.init.poll(&self.cell.inner));
+        Ok(Async::Ready(self.cell.inner.value().clone()))
+    }
+}
+
+impl<T, F, R: IntoFuture> Drop for OnceCellGetOrInitCloned<T, F, R> {
+    fn drop(&mut self) {
+        self.init.cancel(&self.cell.inner);
+    }
+}
+
+impl<T, F, R: IntoFuture> fmt::Debug for OnceCellGetOrInitCloned<T, F, R> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("OnceCellGetOrInitCloned")
+           .field("initializing", &self.init.running)
+           .finish()
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<OnceCell<u32>>();
+    _assert_sync::<OnceCell<u32>>();
+    _assert_send::<OnceCellGetOrInitCloned<u32, fn() -> Result<u32, ()>, Result<u32, ()>>>();
+}
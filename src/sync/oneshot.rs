@@ -175,6 +175,18 @@ impl<T> Sender<T> {
             Ok(Async::NotReady)
         }
     }
+
+    /// Tests to see whether this `Sender`'s corresponding `Receiver` has gone
+    /// away.
+    ///
+    /// This function, unlike `poll_cancel`, can be called from outside the
+    /// context of a task, and never registers the current task for a
+    /// notification. If `true` is returned then the `Receiver` has been
+    /// dropped or closed, and a value sent through `complete` would never be
+    /// received.
+    pub fn is_closed(&self) -> bool {
+        self.inner.complete.load(SeqCst)
+    }
 }
 
 impl<T> Drop for Sender<T> {
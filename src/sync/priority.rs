@@ -0,0 +1,336 @@
+//! A multi-producer, single-consumer, futures-aware priority queue with back
+//! pressure.
+//!
+//! This channel works like the one in the `mpsc` module, except that the
+//! `Receiver` yields the greatest message in the channel first, as ordered by
+//! the message type's `Ord` implementation. Messages which compare equal are
+//! received in the order in which they were sent.
+//!
+//! This makes it possible for urgent messages, such as a request to shut
+//! down, to overtake a backlog of less important ones. A common way to use it
+//! is to send an enum whose variants are declared in increasing order of
+//! priority, or a `(priority, message)` tuple.
+//!
+//! As with `mpsc`, the channel capacity is `buffer + num-senders`: each
+//! sender gets a guaranteed slot, and once the channel is at capacity a
+//! sender which sends a message is parked until the receiver takes a message
+//! out of the channel. Note that this means urgent messages are subject to
+//! back pressure like any other.
+//!
+//! # Disconnection
+//!
+//! When all `Sender` handles have been dropped, it is no longer possible to
+//! send values into the channel. Once the messages remaining in the channel
+//! have been received, the `Receiver` will return `Ok(Ready(None))`.
+//!
+//! If the receiver handle is dropped, then messages can no longer be read out
+//! of the channel. In this case, a `send` will result in an error.
+//!
+//! # Clean Shutdown
+//!
+//! The receiver can call `close`, which prevents any further messages from
+//! being sent into the channel, after which it can consume the channel to
+//! completion before being dropped.
+
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use task::{self, Task};
+use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+
+/// The transmission end of a priority channel which is used to send values.
+///
+/// This is created by the `channel` function.
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+    id: usize,
+}
+
+/// The receiving end of a priority channel which implements the `Stream`
+/// trait, yielding the greatest message in the channel first.
+///
+/// This is created by the `channel` function.
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+/// Error type for sending, used when the receiving end of the channel is
+/// closed or dropped.
+pub struct SendError<T>(T);
+
+struct Inner<T> {
+    buffer: usize,
+    messages: BinaryHeap<Entry<T>>,
+    // Incremented for every message sent, to order messages which compare
+    // equal by when they were sent
+    next_seq: u64,
+    is_open: bool,
+    num_senders: usize,
+    // Senders which sent a message while the channel was at capacity, in the
+    // order in which they'll be unparked
+    parked_senders: VecDeque<(usize, Task)>,
+    recv_task: Option<Task>,
+    next_id: usize,
+}
+
+struct Entry<T> {
+    msg: T,
+    seq: u64,
+}
+
+/// Creates an in-memory priority channel with bounded capacity.
+///
+/// The channel capacity is equal to `buffer + num-senders`. In other words,
+/// each sender gets a guaranteed slot in the channel capacity, and on top of
+/// that there are `buffer` "first come, first serve" slots available to all
+/// senders.
+///
+/// # Examples
+///
+/// ```
+/// use futures::{Future, Sink, Stream};
+/// use futures::sync::priority;
+///
+/// #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// enum Message {
+///     Data(u32),
+///     Shutdown,
+/// }
+///
+/// let (tx, rx) = priority::channel(4);
+/// let tx = tx.send(Message::Data(1)).wait().unwrap();
+/// let tx = tx.send(Message::Data(2)).wait().unwrap();
+/// let tx = tx.send(Message::Shutdown).wait().unwrap();
+/// drop(tx);
+///
+/// let mut rx = rx.wait();
+/// assert_eq!(rx.next(), Some(Ok(Message::Shutdown)));
+/// assert_eq!(rx.next(), Some(Ok(Message::Data(2))));
+/// assert_eq!(rx.next(), Some(Ok(Message::Data(1))));
+/// ```
+pub fn channel<T: Ord>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        buffer: buffer,
+        messages: BinaryHeap::new(),
+        next_seq: 0,
+        is_open: true,
+        num_senders: 1,
+        parked_senders: VecDeque::new(),
+        recv_task: None,
+        next_id: 1,
+    }));
+    let tx = Sender { inner: inner.clone(), id: 0 };
+    let rx = Receiver { inner: inner };
+    (tx, rx)
+}
+
+// Orders entries by their message, and then by the reverse of their sequence
+// number so that the earliest message comes first among equal ones.
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Entry<T>) -> Ordering {
+        self.msg.cmp(&other.msg).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Entry<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Entry<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Entry<T> {}
+
+/*
+ *
+ * ===== impl Sender =====
+ *
+ */
+
+impl<T> Sender<T> {
+    // Returns whether this sender is still waiting to be unparked, updating
+    // the task to notify if so.
+    fn is_parked(&self, state: &mut Inner<T>) -> bool {
+        for &mut (id, ref mut task) in state.parked_senders.iter_mut() {
+            if id == self.id {
+                *task = task::park();
+                return true
+            }
+        }
+        false
+    }
+}
+
+impl<T: Ord> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, msg: T) -> StartSend<T, SendError<T>> {
+        let task = {
+            let mut state = self.inner.lock().unwrap();
+            if !state.is_open {
+                return Err(SendError(msg))
+            }
+            // If the sender is currently blocked, reject the message before
+            // doing any work.
+            if self.is_parked(&mut state) {
+                return Ok(AsyncSink::NotReady(msg))
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.messages.push(Entry { msg: msg, seq: seq });
+            if state.messages.len() > state.buffer {
+                state.parked_senders.push_back((self.id, task::park()));
+            }
+            state.recv_task.take()
+        };
+        if let Some(task) = task {
+            task.unpark();
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        let mut state = self.inner.lock().unwrap();
+        state.num_senders += 1;
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        Sender { inner: self.inner.clone(), id: id }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let task = {
+            let mut state = self.inner.lock().unwrap();
+            state.num_senders -= 1;
+            let id = self.id;
+            state.parked_senders.retain(|&(s, _)| s != id);
+            if state.num_senders > 0 {
+                return
+            }
+            state.recv_task.take()
+        };
+        if let Some(task) = task {
+            task.unpark();
+        }
+    }
+}
+
+/*
+ *
+ * ===== impl Receiver =====
+ *
+ */
+
+impl<T> Receiver<T> {
+    /// Closes the receiving half
+    ///
+    /// This prevents any further messages from being sent on the channel while
+    /// still enabling the receiver to drain messages that are buffered.
+    pub fn close(&mut self) {
+        let tasks = {
+            let mut state = self.inner.lock().unwrap();
+            state.is_open = false;
+            state.parked_senders.drain(..).collect::<Vec<_>>()
+        };
+        for (_, task) in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl<T: Ord> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        let (entry, task) = {
+            let mut state = self.inner.lock().unwrap();
+            match state.messages.pop() {
+                Some(entry) => (entry, state.parked_senders.pop_front()),
+                None => {
+                    if state.num_senders == 0 || !state.is_open {
+                        return Ok(Async::Ready(None))
+                    }
+                    state.recv_task = Some(task::park());
+                    return Ok(Async::NotReady)
+                }
+            }
+        };
+        if let Some((_, task)) = task {
+            task.unpark();
+        }
+        Ok(Async::Ready(Some(entry.msg)))
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sender").finish()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Receiver").finish()
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because receiver is gone")
+    }
+}
+
+impl<T> Error for SendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        "send failed because receiver is gone"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<Sender<u32>>();
+    _assert_send::<Receiver<u32>>();
+}
@@ -0,0 +1,615 @@
+use std::prelude::v1::*;
+
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware reader-writer lock which can be shared between any number
+/// of owners.
+///
+/// Any number of readers may hold the lock at once, or a single writer. Tasks
+/// which can't acquire the lock straight away are parked until it's handed
+/// to them, and never block the thread they're running on. Handles are cheap
+/// to clone, and all clones refer to the same lock.
+///
+/// Which tasks get the lock when it's contended is decided by the lock's
+/// `Preference`. By default writers are preferred, so a steady stream of
+/// readers can't keep a writer waiting forever. Waiting writers are always
+/// served in the order in which they started waiting, and waiting readers are
+/// all admitted at once.
+///
+/// As with `Mutex`, the lock can either be acquired through `read` and
+/// `write`, which borrow the `RwLock`, or through `read_owned` and
+/// `write_owned`, which consume a handle and resolve to guards that can be
+/// carried across combinators such as `and_then`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::sync::RwLock;
+///
+/// let lock = RwLock::new(5);
+///
+/// {
+///     let r1 = lock.read().wait().unwrap();
+///     let r2 = lock.read().wait().unwrap();
+///     assert_eq!(*r1 + *r2, 10);
+///     assert!(lock.try_write().is_none());
+/// }
+///
+/// let mut w = lock.write().wait().unwrap();
+/// *w += 1;
+/// assert!(lock.try_read().is_none());
+/// ```
+pub struct RwLock<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// Which tasks an `RwLock` favors when both readers and writers are waiting
+/// for it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preference {
+    /// Readers may acquire the lock while it's held by other readers even if
+    /// writers are waiting, and all waiting readers are admitted before the
+    /// next writer once a writer releases the lock.
+    ///
+    /// This gives the most concurrency for read-heavy workloads, but writers
+    /// can be starved by a steady stream of readers.
+    Readers,
+    /// Once a writer is waiting no new readers are admitted, and waiting
+    /// writers are served before any waiting readers.
+    ///
+    /// Readers can be starved by a steady stream of writers instead.
+    Writers,
+}
+
+struct Inner<T> {
+    state: sync::Mutex<State>,
+    data: UnsafeCell<T>,
+}
+
+struct State {
+    preference: Preference,
+    // Number of readers holding the lock
+    readers: usize,
+    // Whether a writer is holding the lock
+    writer: bool,
+    waiting_readers: VecDeque<Waiter>,
+    waiting_writers: VecDeque<Waiter>,
+    // Waiters which the lock has been handed to, but which haven't been
+    // polled since to take it. They're already counted in `readers` and
+    // `writer`.
+    granted: Vec<usize>,
+    next_id: usize,
+}
+
+struct Waiter {
+    id: usize,
+    task: Task,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Read,
+    Write,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send + Sync> Sync for Inner<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates a new unlocked `RwLock` protecting the provided data, which
+    /// prefers writers.
+    pub fn new(t: T) -> RwLock<T> {
+        RwLock::with_preference(t, Preference::Writers)
+    }
+
+    /// Creates a new unlocked `RwLock` protecting the provided data, with the
+    /// given preference between readers and writers.
+    pub fn with_preference(t: T, preference: Preference) -> RwLock<T> {
+        RwLock {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    preference: preference,
+                    readers: 0,
+                    writer: false,
+                    waiting_readers: VecDeque::new(),
+                    waiting_writers: VecDeque::new(),
+                    granted: Vec::new(),
+                    next_id: 0,
+                }),
+                data: UnsafeCell::new(t),
+            }),
+        }
+    }
+
+    /// Attempts to acquire this lock for reading immediately, returning
+    /// `None` if that would have to wait.
+    ///
+    /// This function doesn't require a task context.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        if self.inner.try_acquire(Kind::Read) {
+            Some(RwLockReadGuard { inner: &self.inner })
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to acquire this lock for writing immediately, returning
+    /// `None` if that would have to wait.
+    ///
+    /// This function doesn't require a task context.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        if self.inner.try_acquire(Kind::Write) {
+            Some(RwLockWriteGuard { inner: &self.inner })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a future which resolves to a guard once this lock has been
+    /// acquired for reading.
+    ///
+    /// If the future is dropped before it resolves it gives up its place in
+    /// the queue, releasing the lock again if it had already been handed to
+    /// it.
+    ///
+    /// Note that the returned future will never resolve to an error.
+    pub fn read(&self) -> RwLockRead<T> {
+        RwLockRead {
+            inner: &self.inner,
+            id: None,
+        }
+    }
+
+    /// Returns a future which resolves to a guard once this lock has been
+    /// acquired for writing.
+    ///
+    /// If the future is dropped before it resolves it gives up its place in
+    /// the queue, passing on the lock if it had already been handed to it.
+    ///
+    /// Note that the returned future will never resolve to an error.
+    pub fn write(&self) -> RwLockWrite<T> {
+        RwLockWrite {
+            inner: &self.inner,
+            id: None,
+        }
+    }
+
+    /// Consumes this handle and returns a future which resolves once the lock
+    /// has been acquired for reading.
+    ///
+    /// This is like `read`, except that the returned future resolves to an
+    /// `RwLockReadAcquired<T>` which owns its handle to the lock. The original
+    /// handle can be recovered through `RwLockReadAcquired::unlock`.
+    pub fn read_owned(self) -> RwLockReadAcquire<T> {
+        RwLockReadAcquire {
+            inner: Some(self),
+            id: None,
+        }
+    }
+
+    /// Consumes this handle and returns a future which resolves once the lock
+    /// has been acquired for writing.
+    ///
+    /// This is like `write`, except that the returned future resolves to an
+    /// `RwLockWriteAcquired<T>` which owns its handle to the lock. The
+    /// original handle can be recovered through `RwLockWriteAcquired::unlock`.
+    pub fn write_owned(self) -> RwLockWriteAcquire<T> {
+        RwLockWriteAcquire {
+            inner: Some(self),
+            id: None,
+        }
+    }
+
+    /// Returns the preference between readers and writers of this lock.
+    pub fn preference(&self) -> Preference {
+        self.inner.state.lock().unwrap().preference
+    }
+}
+
+impl<T> Clone for RwLock<T> {
+    fn clone(&self) -> RwLock<T> {
+        RwLock { inner: self.inner.clone() }
+    }
+}
+
+impl State {
+    fn can_acquire(&self, kind: Kind) -> bool {
+        if self.writer {
+            return false
+        }
+        match kind {
+            Kind::Read => {
+                self.preference == Preference::Readers ||
+                    self.waiting_writers.is_empty()
+            }
+            Kind::Write => {
+                self.readers == 0 &&
+                    self.waiting_writers.is_empty() &&
+                    (self.preference == Preference::Writers ||
+                     self.waiting_readers.is_empty())
+            }
+        }
+    }
+
+    fn hold(&mut self, kind: Kind) {
+        match kind {
+            Kind::Read => self.readers += 1,
+            Kind::Write => self.writer = true,
+        }
+    }
+
+    fn release(&mut self, kind: Kind) {
+        match kind {
+            Kind::Read => self.readers -= 1,
+            Kind::Write => self.writer = false,
+        }
+    }
+
+    // Hands the lock to as many waiters as the current holders and the
+    // preference allow, returning their tasks to be unparked once the state
+    // lock has been released.
+    fn admit(&mut self) -> Vec<Task> {
+        let mut tasks = Vec::new();
+        if self.writer {
+            return tasks
+        }
+        let writer_first = self.preference == Preference::Writers ||
+                           self.waiting_readers.is_empty();
+        if self.readers == 0 && writer_first {
+            if let Some(waiter) = self.waiting_writers.pop_front() {
+                self.writer = true;
+                self.granted.push(waiter.id);
+                tasks.push(waiter.task);
+                return tasks
+            }
+        }
+        if self.preference == Preference::Readers ||
+           self.waiting_writers.is_empty() {
+            for waiter in self.waiting_readers.drain(..) {
+                self.readers += 1;
+                self.granted.push(waiter.id);
+                tasks.push(waiter.task);
+            }
+        }
+        tasks
+    }
+
+    fn queue(&mut self, kind: Kind) -> &mut VecDeque<Waiter> {
+        match kind {
+            Kind::Read => &mut self.waiting_readers,
+            Kind::Write => &mut self.waiting_writers,
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    fn try_acquire(&self, kind: Kind) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.can_acquire(kind) {
+            state.hold(kind);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Attempts to acquire the lock on behalf of the waiter `id`, queueing it
+    // up if it isn't already waiting.
+    fn poll_acquire(&self, kind: Kind, id: &mut Option<usize>) -> Async<()> {
+        let mut state = self.state.lock().unwrap();
+        match *id {
+            None => {
+                if state.can_acquire(kind) {
+                    state.hold(kind);
+                    return Async::Ready(())
+                }
+                let me = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.queue(kind).push_back(Waiter { id: me, task: task::park() });
+                *id = Some(me);
+                Async::NotReady
+            }
+            Some(me) => {
+                if let Some(pos) = state.granted.iter().position(|&g| g == me) {
+                    state.granted.swap_remove(pos);
+                    *id = None;
+                    return Async::Ready(())
+                }
+                for waiter in state.queue(kind).iter_mut() {
+                    if waiter.id == me {
+                        waiter.task = task::park();
+                    }
+                }
+                Async::NotReady
+            }
+        }
+    }
+
+    // Called when a waiter gives up. Besides releasing the lock if it had
+    // already been handed to this waiter, a writer leaving the queue may
+    // allow waiting readers in.
+    fn cancel(&self, kind: Kind, id: usize) {
+        let tasks = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(pos) = state.granted.iter().position(|&g| g == id) {
+                state.granted.swap_remove(pos);
+                state.release(kind);
+            } else {
+                state.queue(kind).retain(|w| w.id != id);
+            }
+            state.admit()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+
+    fn unlock(&self, kind: Kind) {
+        let tasks = {
+            let mut state = self.state.lock().unwrap();
+            state.release(kind);
+            state.admit()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+/// RAII guard for a lock acquired for reading, returned from `try_read` and
+/// the `RwLockRead` future.
+///
+/// This structure implements `Deref` to the data in the `RwLock<T>`, and the
+/// lock is released when it's dropped.
+pub struct RwLockReadGuard<'a, T: 'a> {
+    inner: &'a Inner<T>,
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.data.get() }
+    }
+}
+
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.inner.unlock(Kind::Read);
+    }
+}
+
+/// RAII guard for a lock acquired for writing, returned from `try_write` and
+/// the `RwLockWrite` future.
+///
+/// This structure implements `Deref` and `DerefMut` to the data in the
+/// `RwLock<T>`, and the lock is released when it's dropped.
+pub struct RwLockWriteGuard<'a, T: 'a> {
+    inner: &'a Inner<T>,
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.data.get() }
+    }
+}
+
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.inner.unlock(Kind::Write);
+    }
+}
+
+/// Future returned by `RwLock::read` which will resolve to a guard when the
+/// lock is acquired for reading.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockRead<'a, T: 'a> {
+    inner: &'a Inner<T>,
+    id: Option<usize>,
+}
+
+impl<'a, T> Future for RwLockRead<'a, T> {
+    type Item = RwLockReadGuard<'a, T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<RwLockReadGuard<'a, T>, ()> {
+        match self.inner.poll_acquire(Kind::Read, &mut self.id) {
+            Async::Ready(()) => Ok(RwLockReadGuard { inner: self.inner }.into()),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<'a, T> Drop for RwLockRead<'a, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.inner.cancel(Kind::Read, id);
+        }
+    }
+}
+
+/// Future returned by `RwLock::write` which will resolve to a guard when the
+/// lock is acquired for writing.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockWrite<'a, T: 'a> {
+    inner: &'a Inner<T>,
+    id: Option<usize>,
+}
+
+impl<'a, T> Future for RwLockWrite<'a, T> {
+    type Item = RwLockWriteGuard<'a, T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<RwLockWriteGuard<'a, T>, ()> {
+        match self.inner.poll_acquire(Kind::Write, &mut self.id) {
+            Async::Ready(()) => Ok(RwLockWriteGuard { inner: self.inner }.into()),
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<'a, T> Drop for RwLockWrite<'a, T> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.inner.cancel(Kind::Write, id);
+        }
+    }
+}
+
+/// Future returned by `RwLock::read_owned` which will resolve when the lock
+/// is acquired for reading.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockReadAcquire<T> {
+    inner: Option<RwLock<T>>,
+    id: Option<usize>,
+}
+
+impl<T> Future for RwLockReadAcquire<T> {
+    type Item = RwLockReadAcquired<T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<RwLockReadAcquired<T>, ()> {
+        let ready = {
+            let lock = self.inner.as_ref().expect("cannot poll RwLockReadAcquire twice");
+            lock.inner.poll_acquire(Kind::Read, &mut self.id)
+        };
+        match ready {
+            Async::Ready(()) => {
+                Ok(RwLockReadAcquired { inner: self.inner.take().unwrap() }.into())
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<T> Drop for RwLockReadAcquire<T> {
+    fn drop(&mut self) {
+        if let (Some(ref lock), Some(id)) = (self.inner.take(), self.id) {
+            lock.inner.cancel(Kind::Read, id);
+        }
+    }
+}
+
+/// Future returned by `RwLock::write_owned` which will resolve when the lock
+/// is acquired for writing.
+#[must_use = "futures do nothing unless polled"]
+pub struct RwLockWriteAcquire<T> {
+    inner: Option<RwLock<T>>,
+    id: Option<usize>,
+}
+
+impl<T> Future for RwLockWriteAcquire<T> {
+    type Item = RwLockWriteAcquired<T>;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<RwLockWriteAcquired<T>, ()> {
+        let ready = {
+            let lock = self.inner.as_ref().expect("cannot poll RwLockWriteAcquire twice");
+            lock.inner.poll_acquire(Kind::Write, &mut self.id)
+        };
+        match ready {
+            Async::Ready(()) => {
+                Ok(RwLockWriteAcquired { inner: self.inner.take().unwrap() }.into())
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
+impl<T> Drop for RwLockWriteAcquire<T> {
+    fn drop(&mut self) {
+        if let (Some(ref lock), Some(id)) = (self.inner.take(), self.id) {
+            lock.inner.cancel(Kind::Write, id);
+        }
+    }
+}
+
+/// Resolved value of the `RwLockReadAcquire<T>` future.
+///
+/// This value, like `RwLockReadGuard<T>`, implements `Deref` to the data in
+/// the lock and releases the lock when dropped. The original `RwLock<T>`
+/// handle can be recovered through the `unlock` method.
+pub struct RwLockReadAcquired<T> {
+    inner: RwLock<T>,
+}
+
+impl<T> RwLockReadAcquired<T> {
+    /// Recovers the original `RwLock<T>`, releasing this lock.
+    pub fn unlock(self) -> RwLock<T> {
+        let lock = self.inner.clone();
+        drop(self);
+        lock
+    }
+}
+
+impl<T> Deref for RwLockReadAcquired<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.inner.data.get() }
+    }
+}
+
+impl<T> Drop for RwLockReadAcquired<T> {
+    fn drop(&mut self) {
+        self.inner.inner.unlock(Kind::Read);
+    }
+}
+
+/// Resolved value of the `RwLockWriteAcquire<T>` future.
+///
+/// This value, like `RwLockWriteGuard<T>`, implements `Deref` and `DerefMut`
+/// to the data in the lock and releases the lock when dropped. The original
+/// `RwLock<T>` handle can be recovered through the `unlock` method.
+pub struct RwLockWriteAcquired<T> {
+    inner: RwLock<T>,
+}
+
+impl<T> RwLockWriteAcquired<T> {
+    /// Recovers the original `RwLock<T>`, releasing this lock.
+    pub fn unlock(self) -> RwLock<T> {
+        let lock = self.inner.clone();
+        drop(self);
+        lock
+    }
+}
+
+impl<T> Deref for RwLockWriteAcquired<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.inner.inner.data.get() }
+    }
+}
+
+impl<T> DerefMut for RwLockWriteAcquired<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.inner.data.get() }
+    }
+}
+
+impl<T> Drop for RwLockWriteAcquired<T> {
+    fn drop(&mut self) {
+        self.inner.inner.unlock(Kind::Write);
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<RwLock<u32>>();
+    _assert_sync::<RwLock<u32>>();
+    _assert_send::<RwLockReadAcquire<u32>>();
+    _assert_send::<RwLockWriteAcquired<u32>>();
+}
@@ -0,0 +1,289 @@
+use std::prelude::v1::*;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware counting semaphore.
+///
+/// A semaphore holds a number of permits which tasks acquire, wait for if
+/// there aren't enough available, and release again once they're done. This
+/// is commonly used to bound how much of some resource is used at once, for
+/// example how many futures spawned onto a thread pool are running a
+/// particular kind of request at any one time.
+///
+/// Handles to a `Semaphore` are cheap to clone, and all clones refer to the
+/// same set of permits. Permits are handed out as `SemaphorePermit` values
+/// which release their permits when dropped, and which own a handle to the
+/// semaphore so they can be moved into other futures and threads.
+///
+/// The semaphore is fair: tasks are given permits in the order in which they
+/// started waiting, so a task waiting for many permits can't be starved by
+/// others repeatedly taking a few at a time.
+///
+/// # Examples
+///
+/// ```
+/// use futures::Future;
+/// use futures::sync::Semaphore;
+///
+/// let sem = Semaphore::new(2);
+///
+/// let a = sem.acquire(1).wait().unwrap();
+/// let b = sem.try_acquire(1).unwrap();
+/// assert!(sem.try_acquire(1).is_none());
+///
+/// drop(a);
+/// assert_eq!(sem.available_permits(), 1);
+/// # drop(b);
+/// ```
+#[derive(Clone)]
+pub struct Semaphore {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: sync::Mutex<State>,
+}
+
+struct State {
+    permits: usize,
+    // Tasks waiting for permits, in the order in which they'll get them
+    waiters: VecDeque<Waiter>,
+    // Waiters which have been handed their permits, but which haven't been
+    // polled since to take them
+    granted: Vec<usize>,
+    next_id: usize,
+}
+
+struct Waiter {
+    id: usize,
+    permits: usize,
+    task: Task,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits available.
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    permits: permits,
+                    waiters: VecDeque::new(),
+                    granted: Vec::new(),
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Returns the number of permits which are currently available.
+    pub fn available_permits(&self) -> usize {
+        self.inner.state.lock().unwrap().permits
+    }
+
+    /// Attempts to acquire `n` permits immediately, returning `None` if there
+    /// aren't enough available or other tasks are already waiting for
+    /// permits.
+    ///
+    /// This function doesn't require a task context.
+    pub fn try_acquire(&self, n: usize) -> Option<SemaphorePermit> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.waiters.is_empty() && state.permits >= n {
+            state.permits -= n;
+            Some(SemaphorePermit { sem: self.clone(), permits: n })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a future which resolves to `n` permits once they're available.
+    ///
+    /// The current task is queued up behind any other tasks already waiting
+    /// for permits when the returned future is first polled. If the future is
+    /// dropped before it resolves it gives up its place in the queue, and any
+    /// permits which had already been handed to it are released.
+    ///
+    /// Note that if `n` is larger than the number of permits that will ever
+    /// be available the returned future never resolves, and holds up all the
+    /// tasks queued behind it.
+    ///
+    /// The returned future will never resolve to an error.
+    pub fn acquire(&self, n: usize) -> SemaphoreAcquire {
+        SemaphoreAcquire {
+            sem: self.clone(),
+            permits: n,
+            id: None,
+        }
+    }
+
+    /// Adds `n` new permits to this semaphore, handing them to waiting tasks
+    /// if there are any.
+    pub fn add_permits(&self, n: usize) {
+        let tasks = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.permits += n;
+            state.admit()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Semaphore")
+           .field("permits", &self.available_permits())
+           .finish()
+    }
+}
+
+impl State {
+    // Hands out permits to waiters at the front of the queue for as long as
+    // there are enough, returning their tasks to be unparked once the state
+    // lock has been released.
+    fn admit(&mut self) -> Vec<Task> {
+        let mut tasks = Vec::new();
+        while self.waiters.front().map(|w| w.permits <= self.permits) == Some(true) {
+            let waiter = self.waiters.pop_front().unwrap();
+            self.permits -= waiter.permits;
+            self.granted.push(waiter.id);
+            tasks.push(waiter.task);
+        }
+        tasks
+    }
+}
+
+/// Future returned by `Semaphore::acquire` which will resolve once the
+/// requested number of permits has been acquired.
+#[must_use = "futures do nothing unless polled"]
+pub struct SemaphoreAcquire {
+    sem: Semaphore,
+    permits: usize,
+    id: Option<usize>,
+}
+
+impl Future for SemaphoreAcquire {
+    type Item = SemaphorePermit;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<SemaphorePermit, ()> {
+        let mut state = self.sem.inner.state.lock().unwrap();
+        match self.id {
+            None => {
+                if state.waiters.is_empty() && state.permits >= self.permits {
+                    state.permits -= self.permits;
+                } else {
+                    let me = state.next_id;
+                    state.next_id = state.next_id.wrapping_add(1);
+                    state.waiters.push_back(Waiter {
+                        id: me,
+                        permits: self.permits,
+                        task: task::park(),
+                    });
+                    self.id = Some(me);
+                    return Ok(Async::NotReady)
+                }
+            }
+            Some(me) => {
+                match state.granted.iter().position(|&g| g == me) {
+                    Some(pos) => {
+                        state.granted.swap_remove(pos);
+                        self.id = None;
+                    }
+                    None => {
+                        for waiter in state.waiters.iter_mut() {
+                            if waiter.id == me {
+                                waiter.task = task::park();
+                            }
+                        }
+                        return Ok(Async::NotReady)
+                    }
+                }
+            }
+        }
+        Ok(SemaphorePermit {
+            sem: self.sem.clone(),
+            permits: mem::replace(&mut self.permits, 0),
+        }.into())
+    }
+}
+
+impl Drop for SemaphoreAcquire {
+    fn drop(&mut self) {
+        let me = match self.id {
+            Some(id) => id,
+            None => return,
+        };
+        let tasks = {
+            let mut state = self.sem.inner.state.lock().unwrap();
+            match state.granted.iter().position(|&g| g == me) {
+                Some(pos) => {
+                    state.granted.swap_remove(pos);
+                    state.permits += self.permits;
+                }
+                None => state.waiters.retain(|w| w.id != me),
+            }
+            // Whether we gave back permits or stopped holding up the queue,
+            // the tasks behind us may be able to go now.
+            state.admit()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+/// Permits acquired from a `Semaphore`, which are released when this is
+/// dropped.
+///
+/// This is returned from `Semaphore::try_acquire` and the `SemaphoreAcquire`
+/// future.
+#[must_use = "permits are released immediately if unused"]
+pub struct SemaphorePermit {
+    sem: Semaphore,
+    permits: usize,
+}
+
+impl SemaphorePermit {
+    /// Returns the number of permits held.
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    /// Consumes this value without releasing its permits, permanently
+    /// removing them from the semaphore.
+    pub fn forget(mut self) {
+        self.permits = 0;
+    }
+}
+
+impl Drop for SemaphorePermit {
+    fn drop(&mut self) {
+        if self.permits > 0 {
+            self.sem.add_permits(self.permits);
+        }
+    }
+}
+
+impl fmt::Debug for SemaphorePermit {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SemaphorePermit")
+           .field("permits", &self.permits)
+           .finish()
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<Semaphore>();
+    _assert_sync::<Semaphore>();
+    _assert_send::<SemaphoreAcquire>();
+    _assert_send::<SemaphorePermit>();
+}
@@ -0,0 +1,206 @@
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::sync::{self, Arc};
+
+use {Async, Future, Poll};
+use task::{self, Task};
+
+/// A futures-aware counter which tasks can wait on to reach zero, also known
+/// as a countdown latch.
+///
+/// The counter is raised with `add` for each piece of outstanding work, and
+/// lowered with `done` as each one finishes. The future returned by `wait`
+/// resolves once the counter reaches zero. Unlike joining a list of futures,
+/// the amount of work doesn't need to be known up front: it can be added to
+/// while other work is still in progress.
+///
+/// Handles to a `WaitGroup` are cheap to clone, and all clones refer to the
+/// same counter.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use futures::Future;
+/// use futures::sync::WaitGroup;
+///
+/// let wg = WaitGroup::new();
+/// for _ in 0..4 {
+///     wg.add(1);
+///     let wg = wg.clone();
+///     thread::spawn(move || {
+///         // ... do some work ...
+///         wg.done();
+///     });
+/// }
+///
+/// wg.wait().wait().unwrap();
+/// assert_eq!(wg.count(), 0);
+/// ```
+#[derive(Clone)]
+pub struct WaitGroup {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: sync::Mutex<State>,
+}
+
+struct State {
+    count: usize,
+    // Incremented every time the count reaches zero
+    generation: usize,
+    waiters: Vec<(usize, Task)>,
+    next_id: usize,
+}
+
+/// Future returned by `WaitGroup::wait` which will resolve once the counter
+/// reaches zero.
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitGroupWait {
+    wg: WaitGroup,
+    // Our id and the generation we started waiting in, once we're waiting
+    waiting: Option<(usize, usize)>,
+}
+
+impl WaitGroup {
+    /// Creates a new `WaitGroup` with a count of zero.
+    pub fn new() -> WaitGroup {
+        WaitGroup::with_count(0)
+    }
+
+    /// Creates a new `WaitGroup` with the given initial count.
+    pub fn with_count(count: usize) -> WaitGroup {
+        WaitGroup {
+            inner: Arc::new(Inner {
+                state: sync::Mutex::new(State {
+                    count: count,
+                    generation: 0,
+                    waiters: Vec::new(),
+                    next_id: 0,
+                }),
+            }),
+        }
+    }
+
+    /// Raises the count by `n`.
+    pub fn add(&self, n: usize) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.count = state.count.checked_add(n).expect("count overflowed");
+    }
+
+    /// Lowers the count by one, waking up all waiting tasks if it reaches
+    /// zero.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the count is already zero.
+    pub fn done(&self) {
+        let tasks = {
+            let mut state = self.inner.state.lock().unwrap();
+            assert!(state.count > 0, "done called more times than add");
+            state.count -= 1;
+            if state.count > 0 {
+                return
+            }
+            state.generation = state.generation.wrapping_add(1);
+            state.waiters.drain(..).map(|(_, task)| task).collect::<Vec<_>>()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+
+    /// Returns the current count.
+    pub fn count(&self) -> usize {
+        self.inner.state.lock().unwrap().count
+    }
+
+    /// Returns a future which resolves once the count is zero.
+    ///
+    /// The future resolves immediately if the count is zero when it's first
+    /// polled. Once the count reaches zero, every task waiting at the time is
+    /// woken up and the future resolves, even if the count is raised again
+    /// before it's polled.
+    ///
+    /// The returned future will never resolve to an error.
+    pub fn wait(&self) -> WaitGroupWait {
+        WaitGroupWait {
+            wg: self.clone(),
+            waiting: None,
+        }
+    }
+}
+
+impl Default for WaitGroup {
+    fn default() -> WaitGroup {
+        WaitGroup::new()
+    }
+}
+
+impl fmt::Debug for WaitGroup {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("WaitGroup")
+           .field("count", &self.count())
+           .finish()
+    }
+}
+
+impl Future for WaitGroupWait {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        let mut state = self.wg.inner.state.lock().unwrap();
+        match self.waiting {
+            None => {
+                if state.count == 0 {
+                    return Ok(Async::Ready(()))
+                }
+                let id = state.next_id;
+                state.next_id = state.next_id.wrapping_add(1);
+                state.waiters.push((id, task::park()));
+                self.waiting = Some((id, state.generation));
+                Ok(Async::NotReady)
+            }
+            Some((id, generation)) => {
+                if state.generation != generation {
+                    self.waiting = None;
+                    return Ok(Async::Ready(()))
+                }
+                for &mut (w, ref mut task) in state.waiters.iter_mut() {
+                    if w == id && !task.is_current() {
+                        *task = task::park();
+                    }
+                }
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+impl Drop for WaitGroupWait {
+    fn drop(&mut self) {
+        if let Some((id, _)) = self.waiting {
+            let mut state = self.wg.inner.state.lock().unwrap();
+            state.waiters.retain(|&(w, _)| w != id);
+        }
+    }
+}
+
+impl fmt::Debug for WaitGroupWait {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("WaitGroupWait")
+           .field("waiting", &self.waiting.is_some())
+           .finish()
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<WaitGroup>();
+    _assert_sync::<WaitGroup>();
+    _assert_send::<WaitGroupWait>();
+}
@@ -0,0 +1,314 @@
+//! A single-producer, multi-consumer, futures-aware channel which only
+//! retains the latest value sent.
+//!
+//! This is useful for propagating state, such as configuration, which
+//! consumers are only interested in the current version of. The `Sender`
+//! overwrites the value held by the channel, and each `Receiver` is a
+//! `Stream` which yields the current value whenever it has changed since the
+//! receiver last saw it. Values which are overwritten before a receiver gets
+//! to see them are skipped.
+//!
+//! Both halves also give access to the current value through `borrow`.
+//!
+//! # Disconnection
+//!
+//! When the `Sender` is dropped, receivers yield the latest value if they
+//! haven't seen it yet and then terminate. If all receivers have been
+//! dropped, `send` fails and hands back the value.
+
+use std::prelude::v1::*;
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+
+use task::{self, Task};
+use {Async, AsyncSink, Poll, StartSend, Sink, Stream};
+
+/// The transmission end of a watch channel which is used to update the value.
+///
+/// This is created by the `channel` function.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+/// The receiving end of a watch channel, which is a `Stream` of the values
+/// sent on the channel.
+///
+/// This is created by the `channel` function. Cloning a receiver creates
+/// another one which has seen the same versions of the value as the
+/// original.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+    // The version of the value this receiver last saw
+    version: usize,
+    id: usize,
+}
+
+/// A reference to the value held by a watch channel, returned from `borrow`.
+///
+/// The sender can't update the value while this reference is held. Polling a
+/// receiver on the same thread is fine, but once a new value has been sent it
+/// can't be read out until the reference is released.
+pub struct Ref<'a, T: 'a> {
+    guard: RwLockReadGuard<'a, T>,
+}
+
+/// Error type for sending, used when all receivers are gone.
+pub struct SendError<T>(T);
+
+struct Inner<T> {
+    value: RwLock<T>,
+    // Only changed while `value` is locked for writing, so it always matches
+    // the value seen through a read lock. `value` is never locked while
+    // `state` is held, as a `Ref` may be holding it across calls to `send` or
+    // `poll`.
+    version: AtomicUsize,
+    state: Mutex<State>,
+}
+
+struct State {
+    closed: bool,
+    num_receivers: usize,
+    // Receivers which are waiting for a new value, keyed by their id
+    waiters: Vec<(usize, Task)>,
+    next_id: usize,
+}
+
+/// Creates a new watch channel holding `initial`, returning the sender and
+/// receiver halves.
+///
+/// The returned receiver considers the initial value already seen, so it'll
+/// only yield values sent after this call. The initial value is available
+/// through `borrow`.
+///
+/// # Examples
+///
+/// ```
+/// use futures::{Future, Stream};
+/// use futures::sync::watch;
+///
+/// let (tx, rx) = watch::channel("initial");
+/// assert_eq!(*rx.borrow(), "initial");
+///
+/// tx.send("first").unwrap();
+/// tx.send("second").unwrap();
+/// drop(tx);
+///
+/// assert_eq!(rx.collect().wait(), Ok(vec!["second"]));
+/// ```
+pub fn channel<T: Clone>(initial: T) -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        value: RwLock::new(initial),
+        version: AtomicUsize::new(0),
+        state: Mutex::new(State {
+            closed: false,
+            num_receivers: 1,
+            waiters: Vec::new(),
+            next_id: 1,
+        }),
+    });
+    let tx = Sender { inner: inner.clone() };
+    let rx = Receiver { inner: inner, version: 0, id: 0 };
+    (tx, rx)
+}
+
+impl<T> Inner<T> {
+    fn borrow(&self) -> Ref<T> {
+        Ref { guard: self.value.read().unwrap() }
+    }
+}
+
+impl State {
+    fn take_waiters(&mut self) -> Vec<Task> {
+        self.waiters.drain(..).map(|(_, task)| task).collect()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Replaces the value held by the channel, notifying all receivers.
+    ///
+    /// Fails only if all receivers have been dropped, in which case the value
+    /// is handed back and the held value is left unchanged.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.inner.state.lock().unwrap().num_receivers == 0 {
+            return Err(SendError(value))
+        }
+        {
+            let mut slot = self.inner.value.write().unwrap();
+            *slot = value;
+            self.inner.version.fetch_add(1, SeqCst);
+        }
+        // Receivers check the version after registering themselves, so any
+        // which missed the new one are in the list we take here.
+        let tasks = self.inner.state.lock().unwrap().take_waiters();
+        for task in tasks {
+            task.unpark();
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the value currently held by the channel.
+    pub fn borrow(&self) -> Ref<T> {
+        self.inner.borrow()
+    }
+}
+
+impl<T> Sink for Sender<T> {
+    type SinkItem = T;
+    type SinkError = SendError<T>;
+
+    fn start_send(&mut self, value: T) -> StartSend<T, SendError<T>> {
+        try!(Sender::send(self, value));
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), SendError<T>> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let tasks = {
+            let mut state = self.inner.state.lock().unwrap();
+            state.closed = true;
+            state.take_waiters()
+        };
+        for task in tasks {
+            task.unpark();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Returns a reference to the value currently held by the channel.
+    ///
+    /// This doesn't mark the value as seen by this receiver.
+    pub fn borrow(&self) -> Ref<T> {
+        self.inner.borrow()
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        loop {
+            // Only lock the value once there's something new in it, so that a
+            // `Ref` held by this thread doesn't get stuck behind a `send`
+            // waiting for it to be released.
+            if self.inner.version.load(SeqCst) != self.version {
+                let value = self.inner.value.read().unwrap();
+                self.version = self.inner.version.load(SeqCst);
+                return Ok(Async::Ready(Some(value.clone())))
+            }
+
+            let mut state = self.inner.state.lock().unwrap();
+            // A value may have been sent since we looked, in which case the
+            // sender may already have taken the waiters.
+            if self.inner.version.load(SeqCst) != self.version {
+                continue
+            }
+            if state.closed {
+                return Ok(Async::Ready(None))
+            }
+
+            let task = task::park();
+            let id = self.id;
+            match state.waiters.iter().position(|&(w, _)| w == id) {
+                Some(pos) => state.waiters[pos].1 = task,
+                None => state.waiters.push((id, task)),
+            }
+            return Ok(Async::NotReady)
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Receiver<T> {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_receivers += 1;
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        Receiver {
+            inner: self.inner.clone(),
+            version: self.version,
+            id: id,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.num_receivers -= 1;
+        let id = self.id;
+        state.waiters.retain(|&(w, _)| w != id);
+    }
+}
+
+impl<'a, T> Deref for Ref<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Sender").finish()
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Receiver").finish()
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Ref<'a, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(fmt)
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("SendError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "send failed because all receivers are gone")
+    }
+}
+
+impl<T> Error for SendError<T>
+    where T: Any
+{
+    fn description(&self) -> &str {
+        "send failed because all receivers are gone"
+    }
+}
+
+impl<T> SendError<T> {
+    /// Returns the value that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<Sender<u32>>();
+    _assert_send::<Receiver<u32>>();
+}
@@ -0,0 +1,237 @@
+use std::prelude::v1::*;
+
+use std::cell::RefCell;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::thread;
+
+use {Future, Poll, Async};
+use stack::Stack;
+use super::{spawn, Spawn, ThreadUnpark, UnparkEvent, Events};
+
+// Identifier inserted into the ready set when the future passed to
+// `run_until` is unparked. Spawned futures are identified by their index in
+// `LocalPool::tasks` instead, which can never reach this value.
+const MAIN: usize = !0;
+
+type LocalFuture = Box<Future<Item = (), Error = ()>>;
+
+/// A single-threaded executor which runs any number of futures on the current
+/// thread.
+///
+/// Unlike `Spawn::execute`, futures spawned onto a `LocalPool` do not need to
+/// be `Send`, so they're free to share state through `Rc` and `RefCell`. All
+/// spawned futures make progress whenever the pool is driven through either
+/// `run` or `run_until`, and only the futures which have been unparked since
+/// they were last polled are polled again.
+///
+/// A `LocalPool` itself is not `Send` and is tied to the thread that created
+/// it. To spawn more futures from within a future running on the pool, use a
+/// `LocalSpawner` handle obtained from the `spawner` method.
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+/// use futures::future;
+/// use futures::executor::LocalPool;
+///
+/// let mut pool = LocalPool::new();
+/// let hits = Rc::new(Cell::new(0));
+///
+/// for _ in 0..3 {
+///     let hits = hits.clone();
+///     pool.spawn(future::lazy(move || {
+///         hits.set(hits.get() + 1);
+///         Ok(())
+///     }));
+/// }
+///
+/// pool.run();
+/// assert_eq!(hits.get(), 3);
+/// ```
+pub struct LocalPool {
+    // Futures which have been spawned onto this pool. Slots of futures that
+    // have finished are recorded in `free` and reused by later spawns.
+    tasks: Vec<Option<Spawn<LocalFuture>>>,
+    free: Vec<usize>,
+
+    // Set of identifiers of futures which have been unparked since they were
+    // last polled, filled in through the `UnparkEvent` attached to each task.
+    ready: Arc<Stack<usize>>,
+
+    // Used to block the thread that owns this pool until a notification
+    // arrives.
+    unpark: Arc<ThreadUnpark>,
+
+    // Futures spawned through the pool or a `LocalSpawner` which have yet to
+    // be assigned a slot in `tasks`.
+    incoming: Rc<RefCell<Vec<LocalFuture>>>,
+}
+
+/// A handle to a `LocalPool` which can be used to spawn futures onto it.
+///
+/// This is created by the `LocalPool::spawner` method and is typically moved
+/// into futures running on the pool itself so they can spawn more work.
+#[derive(Clone)]
+pub struct LocalSpawner {
+    incoming: Weak<RefCell<Vec<LocalFuture>>>,
+}
+
+impl LocalPool {
+    /// Creates a new, empty pool of futures bound to the current thread.
+    pub fn new() -> LocalPool {
+        LocalPool {
+            tasks: Vec::new(),
+            free: Vec::new(),
+            ready: Arc::new(Stack::new()),
+            unpark: Arc::new(ThreadUnpark::new(thread::current())),
+            incoming: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Returns a handle which can be used to spawn futures onto this pool.
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner {
+            incoming: Rc::downgrade(&self.incoming),
+        }
+    }
+
+    /// Spawns a future onto this pool.
+    ///
+    /// The future will not make any progress until the pool is driven through
+    /// either `run` or `run_until`.
+    pub fn spawn<F>(&self, f: F)
+        where F: Future<Item = (), Error = ()> + 'static,
+    {
+        self.incoming.borrow_mut().push(Box::new(f));
+    }
+
+    /// Runs all spawned futures on the current thread until they have all
+    /// completed.
+    ///
+    /// This function will block the current thread while none of the futures
+    /// on this pool are able to make progress.
+    pub fn run(&mut self) {
+        loop {
+            self.poll_ready();
+            if self.free.len() == self.tasks.len() &&
+               self.incoming.borrow().is_empty() {
+                return
+            }
+            self.park();
+        }
+    }
+
+    /// Runs all spawned futures on the current thread until the future `f`
+    /// completes, returning its result.
+    ///
+    /// The future `f` is polled on the current thread alongside the spawned
+    /// futures, which is useful for waiting on a result produced by one of
+    /// them. Any spawned futures which have not completed by the time `f`
+    /// resolves are kept in the pool and will be resumed by the next call to
+    /// `run` or `run_until`.
+    pub fn run_until<F: Future>(&mut self, f: F) -> Result<F::Item, F::Error> {
+        let mut main = spawn(f);
+        let mut notified = true;
+        loop {
+            if notified {
+                let res = poll_with_event(&mut main, &self.unpark, &self.ready,
+                                          MAIN);
+                if let Async::Ready(e) = try!(res) {
+                    return Ok(e)
+                }
+            }
+            notified = self.poll_ready();
+            if !notified {
+                self.park();
+            }
+        }
+    }
+
+    // Moves all futures from `incoming` into `tasks`, flagging them as ready
+    // to be polled for the first time.
+    fn spawn_incoming(&mut self) {
+        let incoming = mem::replace(&mut *self.incoming.borrow_mut(), Vec::new());
+        for future in incoming {
+            let task = spawn(future);
+            let idx = match self.free.pop() {
+                Some(idx) => {
+                    self.tasks[idx] = Some(task);
+                    idx
+                }
+                None => {
+                    self.tasks.push(Some(task));
+                    self.tasks.len() - 1
+                }
+            };
+            self.ready.push(idx);
+        }
+    }
+
+    // Polls all spawned futures which have been unparked since they were last
+    // polled, returning whether the future passed to `run_until` was also
+    // unparked.
+    fn poll_ready(&mut self) -> bool {
+        self.spawn_incoming();
+
+        let mut main = false;
+        for idx in self.ready.drain() {
+            if idx == MAIN {
+                main = true;
+                continue
+            }
+
+            let done = match self.tasks[idx] {
+                Some(ref mut task) => {
+                    match poll_with_event(task, &self.unpark, &self.ready, idx) {
+                        Ok(Async::NotReady) => false,
+                        Ok(Async::Ready(())) | Err(()) => true,
+                    }
+                }
+                // This future already completed and we're seeing a stale
+                // notification, so there's nothing to do.
+                None => continue,
+            };
+            if done {
+                self.tasks[idx] = None;
+                self.free.push(idx);
+            }
+        }
+        main
+    }
+
+    // Blocks the current thread until a notification arrives, unless there
+    // are futures waiting to be spawned.
+    fn park(&self) {
+        if self.incoming.borrow().is_empty() {
+            self.unpark.park();
+        }
+    }
+}
+
+impl LocalSpawner {
+    /// Spawns a future onto the pool this handle was created from.
+    ///
+    /// The future will be polled the next time the pool is driven, which is
+    /// immediately after the current future yields if this is called from a
+    /// future running on the pool. If the pool has already been dropped then
+    /// the future is dropped immediately.
+    pub fn spawn<F>(&self, f: F)
+        where F: Future<Item = (), Error = ()> + 'static,
+    {
+        if let Some(incoming) = self.incoming.upgrade() {
+            incoming.borrow_mut().push(Box::new(f));
+        }
+    }
+}
+
+fn poll_with_event<F: Future>(task: &mut Spawn<F>,
+                              unpark: &Arc<ThreadUnpark>,
+                              ready: &Arc<Stack<usize>>,
+                              id: usize) -> Poll<F::Item, F::Error> {
+    let event = UnparkEvent::new(ready.clone(), id);
+    task.enter_with_events(unpark.clone(), Events::One(event), |f| f.poll())
+}
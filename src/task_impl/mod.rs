@@ -15,10 +15,12 @@ use self::unpark_mutex::UnparkMutex;
 
 mod task_rc;
 mod data;
+mod local_pool;
 #[allow(deprecated)]
 #[cfg(feature = "with-deprecated")]
 pub use self::task_rc::TaskRc;
 pub use self::data::LocalKey;
+pub use self::local_pool::{LocalPool, LocalSpawner};
 
 thread_local!(static CURRENT_TASK: Cell<(*const Task, *const data::LocalMap)> = {
     Cell::new((0 as *const _, 0 as *const _))
@@ -389,11 +391,22 @@ impl<S: Stream> Spawn<S> {
 impl<T> Spawn<T> {
     fn enter<F, R>(&mut self, unpark: Arc<Unpark>, f: F) -> R
         where F: FnOnce(&mut T) -> R
+    {
+        self.enter_with_events(unpark, Events::new(), f)
+    }
+
+    // Like `enter`, except that the task handed out by `task::park` will also
+    // trigger `events` whenever it's unparked.
+    fn enter_with_events<F, R>(&mut self,
+                               unpark: Arc<Unpark>,
+                               events: Events,
+                               f: F) -> R
+        where F: FnOnce(&mut T) -> R
     {
         let task = Task {
             id: self.id,
             unpark: unpark,
-            events: Events::new(),
+            events: events,
         };
         let obj = &mut self.obj;
         set(&task, &self.data, || f(obj))
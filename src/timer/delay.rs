@@ -0,0 +1,95 @@
+use std::prelude::v1::*;
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use std::time::Instant;
+
+use {Future, Poll, Async};
+use task;
+use super::{Timer, TimerError, Entry, PENDING, FIRED};
+
+/// A future which resolves once a point in time has been reached.
+///
+/// This is created by the `delay` and `delay_until` functions, or the methods
+/// of the same name on `Timer`. The timer isn't registered with the background
+/// thread until the future is first polled, and dropping the future cancels
+/// it.
+#[must_use = "futures do nothing unless polled"]
+pub struct Delay {
+    timer: Timer,
+    deadline: Instant,
+    entry: Option<Arc<Entry>>,
+}
+
+pub fn new(timer: Timer, deadline: Instant) -> Delay {
+    Delay {
+        timer: timer,
+        deadline: deadline,
+        entry: None,
+    }
+}
+
+impl Delay {
+    /// Returns the instant at which this future resolves.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Resets this future to resolve at the instant `at` instead.
+    ///
+    /// This can be called whether or not the future has already resolved, and
+    /// the future can be polled again afterwards.
+    pub fn reset(&mut self, at: Instant) {
+        self.deadline = at;
+        self.entry = None;
+    }
+
+    /// Returns whether the deadline of this future has been reached.
+    pub fn is_elapsed(&self) -> bool {
+        match self.entry {
+            Some(ref entry) => entry.state.load(SeqCst) == FIRED,
+            None => Instant::now() >= self.deadline,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Item = ();
+    type Error = TimerError;
+
+    fn poll(&mut self) -> Poll<(), TimerError> {
+        if let Some(ref entry) = self.entry {
+            *entry.task.lock().unwrap() = Some(task::park());
+            return match entry.state.load(SeqCst) {
+                PENDING => Ok(Async::NotReady),
+                FIRED => Ok(Async::Ready(())),
+                _ => Err(TimerError),
+            }
+        }
+
+        // There's no need to bother the background thread if we're already
+        // past the deadline.
+        if Instant::now() >= self.deadline {
+            return Ok(Async::Ready(()))
+        }
+
+        let entry = Arc::new(Entry {
+            deadline: self.deadline,
+            state: AtomicUsize::new(PENDING),
+            task: Mutex::new(Some(task::park())),
+        });
+        try!(self.timer.register(entry.clone()));
+        self.entry = Some(entry);
+        Ok(Async::NotReady)
+    }
+}
+
+impl fmt::Debug for Delay {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Delay")
+           .field("deadline", &self.deadline)
+           .finish()
+    }
+}
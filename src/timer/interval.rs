@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use {Poll, Async};
+use future::Future;
+use stream::Stream;
+use super::{Delay, TimerError};
+
+/// A stream which yields `()` at a fixed period.
+///
+/// This is created by the `interval` and `interval_at` functions, or the
+/// methods of the same name on `Timer`. The stream never terminates.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Interval {
+    delay: Delay,
+    period: Duration,
+}
+
+pub fn new(delay: Delay, period: Duration) -> Interval {
+    Interval {
+        delay: delay,
+        period: period,
+    }
+}
+
+impl Interval {
+    /// Returns the period of this stream.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+impl Stream for Interval {
+    type Item = ();
+    type Error = TimerError;
+
+    fn poll(&mut self) -> Poll<Option<()>, TimerError> {
+        try_ready!(self.delay.poll());
+        let next = self.delay.deadline() + self.period;
+        self.delay.reset(next);
+        Ok(Async::Ready(Some(())))
+    }
+}
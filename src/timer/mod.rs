@@ -0,0 +1,393 @@
+//! Timers for futures and streams
+//!
+//! This module contains a `Delay` future, which resolves once a point in time
+//! has been reached, and an `Interval` stream, which yields at a fixed period.
+//! Both are driven by a `Timer`, which runs a hashed timer wheel on a
+//! dedicated background thread and unparks the tasks waiting on timers as
+//! they fire. As such they don't need any particular executor and work just
+//! as well with `Future::wait`, a thread pool or a custom `Executor`.
+//!
+//! Most code can use the `delay` and `interval` functions, which share a
+//! default `Timer` that's created the first time it's needed. Separate timers
+//! with a different resolution can be created through `Builder`.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//! use futures::Future;
+//! use futures::timer;
+//!
+//! let start = Instant::now();
+//! timer::delay(Duration::from_millis(10)).wait().unwrap();
+//! assert!(start.elapsed() >= Duration::from_millis(10));
+//! ```
+
+use std::prelude::v1::*;
+
+use std::error::Error;
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, Mutex, Condvar, Once};
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Future;
+use task::Task;
+
+mod delay;
+mod interval;
+mod wheel;
+
+pub use self::delay::Delay;
+pub use self::interval::Interval;
+
+/// A handle to a timer wheel running on a background thread.
+///
+/// Handles are cheap to clone and can be sent to other threads, and all
+/// clones refer to the same timer. The background thread keeps running until
+/// all handles, along with all `Delay` and `Interval` instances created from
+/// them, have been dropped.
+///
+/// Timers only fire on the ticks of the wheel, so a `Delay` may resolve up to
+/// one tick later than its deadline, but never earlier.
+#[derive(Clone)]
+pub struct Timer {
+    handle: Arc<Handle>,
+}
+
+/// A builder for configuring and creating a new `Timer`.
+pub struct Builder {
+    tick: Duration,
+    num_slots: usize,
+    name: Option<String>,
+}
+
+/// A source of time and timers.
+///
+/// This is what the `timeout` combinators on futures and streams use to find
+/// out the current time and to wait for their deadlines, and is implemented by
+/// `Timer`. Tests can implement it themselves to control how time passes.
+pub trait Clock {
+    /// The future returned by `delay_until`.
+    type Delay: Future<Item = (), Error = TimerError>;
+
+    /// Returns the current instant according to this clock.
+    fn now(&self) -> Instant;
+
+    /// Creates a future which resolves once this clock reaches the instant
+    /// `at`.
+    fn delay_until(&self, at: Instant) -> Self::Delay;
+}
+
+/// Error returned from a future or stream with a timeout applied.
+///
+/// This is the error of the `Timeout` combinators created by
+/// `Future::timeout` and `Stream::timeout`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeoutError<E> {
+    /// The deadline was reached before the future resolved or the stream
+    /// yielded its next item.
+    Elapsed,
+    /// The underlying future or stream resolved to an error.
+    Inner(E),
+}
+
+/// Error returned from a `Delay` or `Interval` when its timer has shut down.
+///
+/// This only happens if the timer's background thread has panicked, after
+/// which no timers created from it will ever fire.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimerError;
+
+// Dropping the last `Handle` shuts down the background thread, which itself
+// only holds on to `Inner`.
+struct Handle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    // Notified whenever entries are registered or the timer is shut down
+    cvar: Condvar,
+}
+
+struct State {
+    // Entries registered since the background thread last checked
+    new: Vec<Arc<Entry>>,
+    shutdown: bool,
+    // Set once the background thread has exited
+    dead: bool,
+}
+
+// A timer registered with the wheel, shared between a `Delay` and the
+// background thread.
+struct Entry {
+    deadline: Instant,
+    state: AtomicUsize,
+    task: Mutex<Option<Task>>,
+}
+
+const PENDING: usize = 0;
+const FIRED: usize = 1;
+const ERROR: usize = 2;
+
+fn _assert() {
+    fn _assert_send<T: Send>() {}
+    fn _assert_sync<T: Sync>() {}
+    _assert_send::<Timer>();
+    _assert_sync::<Timer>();
+    _assert_send::<Delay>();
+    _assert_send::<Interval>();
+}
+
+/// Creates a future which resolves once `dur` has elapsed, using the default
+/// timer.
+pub fn delay(dur: Duration) -> Delay {
+    default_timer().delay(dur)
+}
+
+/// Creates a future which resolves at the instant `at`, using the default
+/// timer.
+pub fn delay_until(at: Instant) -> Delay {
+    default_timer().delay_until(at)
+}
+
+/// Creates a stream which yields every `dur`, starting `dur` from now, using
+/// the default timer.
+pub fn interval(dur: Duration) -> Interval {
+    default_timer().interval(dur)
+}
+
+/// Creates a stream which yields first at the instant `at` and every `dur`
+/// after that, using the default timer.
+pub fn interval_at(at: Instant, dur: Duration) -> Interval {
+    default_timer().interval_at(at, dur)
+}
+
+// The default timer is created on first use and lives for the rest of the
+// program.
+fn default_timer() -> &'static Timer {
+    static INIT: Once = Once::new();
+    static mut DEFAULT: *const Timer = ptr::null();
+
+    unsafe {
+        INIT.call_once(|| {
+            DEFAULT = Box::into_raw(Box::new(Builder::new()
+                .name("futures-timer")
+                .create()));
+        });
+        &*DEFAULT
+    }
+}
+
+impl Timer {
+    /// Creates a new timer with the default configuration.
+    ///
+    /// This spawns a new background thread. See `Builder` for the defaults
+    /// and how to change them.
+    pub fn new() -> Timer {
+        Builder::new().create()
+    }
+
+    /// Creates a future which resolves once `dur` has elapsed.
+    pub fn delay(&self, dur: Duration) -> Delay {
+        self.delay_until(Instant::now() + dur)
+    }
+
+    /// Creates a future which resolves at the instant `at`.
+    ///
+    /// If `at` is in the past the future will resolve immediately.
+    pub fn delay_until(&self, at: Instant) -> Delay {
+        delay::new(self.clone(), at)
+    }
+
+    /// Creates a stream which yields every `dur`, starting `dur` from now.
+    pub fn interval(&self, dur: Duration) -> Interval {
+        self.interval_at(Instant::now() + dur, dur)
+    }
+
+    /// Creates a stream which yields first at the instant `at` and every
+    /// `dur` after that.
+    ///
+    /// Each deadline is computed from the previous one rather than from when
+    /// the stream was polled, so the stream doesn't drift if it's polled late.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `dur` is zero.
+    pub fn interval_at(&self, at: Instant, dur: Duration) -> Interval {
+        assert!(dur > Duration::new(0, 0), "interval period must be non-zero");
+        interval::new(self.delay_until(at), dur)
+    }
+
+    // Hands a new entry to the background thread, failing if it has exited.
+    fn register(&self, entry: Arc<Entry>) -> Result<(), TimerError> {
+        let inner = &self.handle.inner;
+        let mut state = inner.state.lock().unwrap();
+        if state.dead {
+            return Err(TimerError)
+        }
+        state.new.push(entry);
+        inner.cvar.notify_one();
+        Ok(())
+    }
+}
+
+/// Returns a handle to the default timer, which is shared by the free
+/// functions in this module and the `timeout` combinators.
+impl Default for Timer {
+    fn default() -> Timer {
+        default_timer().clone()
+    }
+}
+
+impl Clock for Timer {
+    type Delay = Delay;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn delay_until(&self, at: Instant) -> Delay {
+        Timer::delay_until(self, at)
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Timer").finish()
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().shutdown = true;
+        self.inner.cvar.notify_one();
+    }
+}
+
+impl Builder {
+    /// Creates a new builder with a tick duration of one millisecond and 4096
+    /// slots in the wheel.
+    pub fn new() -> Builder {
+        Builder {
+            tick: Duration::from_millis(1),
+            num_slots: 4096,
+            name: None,
+        }
+    }
+
+    /// Sets the duration of each tick of the wheel.
+    ///
+    /// This is the resolution of the timer: timers fire on the first tick at
+    /// or after their deadline. Longer ticks mean more timers which are due
+    /// around the same time are fired together by the background thread.
+    pub fn tick_duration(&mut self, tick: Duration) -> &mut Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Sets the number of slots in the wheel.
+    ///
+    /// Timers whose deadline is more than a full revolution of the wheel away
+    /// are still supported, but share slots with nearer timers, so this should
+    /// be large enough to cover most of the timeouts used with this timer.
+    pub fn num_slots(&mut self, num_slots: usize) -> &mut Self {
+        self.num_slots = num_slots;
+        self
+    }
+
+    /// Sets the name of the timer's background thread.
+    pub fn name<S: Into<String>>(&mut self, name: S) -> &mut Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Creates a new `Timer` with the configured parameters, spawning its
+    /// background thread.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the tick duration or number of slots is zero.
+    pub fn create(&mut self) -> Timer {
+        let wheel = wheel::Wheel::new(self.tick, self.num_slots);
+        let inner = Arc::new(Inner {
+            state: Mutex::new(State {
+                new: Vec::new(),
+                shutdown: false,
+                dead: false,
+            }),
+            cvar: Condvar::new(),
+        });
+
+        let inner2 = inner.clone();
+        let mut thread_builder = thread::Builder::new();
+        if let Some(ref name) = self.name {
+            thread_builder = thread_builder.name(name.clone());
+        }
+        thread_builder.spawn(move || {
+            let _dead = Dead(&inner2);
+            wheel::run(&inner2, wheel);
+        }).unwrap();
+
+        Timer {
+            handle: Arc::new(Handle { inner: inner }),
+        }
+    }
+}
+
+// Marks the timer as dead when the background thread exits, however it does
+// so, so that new entries fail to register instead of never firing.
+struct Dead<'a>(&'a Inner);
+
+impl<'a> Drop for Dead<'a> {
+    fn drop(&mut self) {
+        let mut state = match self.0.state.lock() {
+            Ok(state) => state,
+            Err(e) => e.into_inner(),
+        };
+        state.dead = true;
+        for entry in state.new.drain(..) {
+            wheel::fire(&entry, ERROR);
+        }
+    }
+}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "timer has shut down")
+    }
+}
+
+impl Error for TimerError {
+    fn description(&self) -> &str {
+        "timer has shut down"
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TimeoutError<E> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeoutError::Elapsed => write!(fmt, "deadline has elapsed"),
+            TimeoutError::Inner(ref e) => e.fmt(fmt),
+        }
+    }
+}
+
+impl<E: Error> Error for TimeoutError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            TimeoutError::Elapsed => "deadline has elapsed",
+            TimeoutError::Inner(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            TimeoutError::Elapsed => None,
+            TimeoutError::Inner(ref e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! The hashed timer wheel run on a `Timer`'s background thread.
+//!
+//! Time is divided into ticks of a fixed duration, counted from when the wheel
+//! was created, and each tick maps to one of a fixed number of slots. A timer
+//! is stored in the slot for the first tick at or after its deadline, and each
+//! time a tick elapses the timers in its slot which are due are fired. Timers
+//! more than one revolution of the wheel away simply stay in their slot until
+//! a later visit.
+
+use std::prelude::v1::*;
+
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::Ordering::SeqCst;
+use std::time::{Duration, Instant};
+
+use super::{Inner, Entry, FIRED, ERROR};
+
+pub struct Wheel {
+    start: Instant,
+    tick_nanos: u64,
+    slots: Vec<Vec<Arc<Entry>>>,
+    // Next tick which has yet to be processed
+    elapsed: u64,
+    // Number of entries across all slots
+    len: usize,
+}
+
+impl Wheel {
+    pub fn new(tick: Duration, num_slots: usize) -> Wheel {
+        let tick_nanos = tick.as_secs() * 1_000_000_000 +
+                         tick.subsec_nanos() as u64;
+        assert!(tick_nanos > 0, "timer tick duration must be non-zero");
+        assert!(num_slots > 0, "timer must have at least one slot");
+        Wheel {
+            start: Instant::now(),
+            tick_nanos: tick_nanos,
+            slots: (0..num_slots).map(|_| Vec::new()).collect(),
+            elapsed: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the instant at which the earliest pending entry is due, or
+    /// `None` if there aren't any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.len == 0 {
+            return None
+        }
+
+        // Walk the slots in the order they'll be visited. An entry is at the
+        // earliest due on the first visit to its slot, so the first one found
+        // to be due then is the earliest; entries a revolution or more away
+        // are kept track of in case nothing else is pending.
+        let num_slots = self.slots.len() as u64;
+        let mut earliest = None;
+        for i in 0..num_slots {
+            let tick = self.elapsed + i;
+            for entry in &self.slots[(tick % num_slots) as usize] {
+                // Entries whose `Delay` has gone away are cleaned up the next
+                // time their slot is processed, no need to wake up for them
+                if Arc::strong_count(entry) == 1 {
+                    continue
+                }
+                let due = self.deadline_tick(entry.deadline);
+                if due <= tick {
+                    return Some(self.tick_start(tick))
+                }
+                earliest = Some(earliest.map_or(due, |e: u64| e.min(due)));
+            }
+        }
+        earliest.map(|t| self.tick_start(t))
+    }
+
+    /// Processes all ticks which have elapsed by `now`, firing the entries
+    /// that are due.
+    pub fn advance(&mut self, now: Instant) {
+        let now_tick = self.tick_at(now);
+        if now_tick < self.elapsed {
+            return
+        }
+
+        // If there's nothing in the wheel there's no need to go through every
+        // tick that's passed since we last looked.
+        if self.len > 0 {
+            // Every entry due by now lives in the slot of a tick between
+            // `elapsed` and `now_tick`, so even after a long pause we only need
+            // to look at each slot once.
+            let ticks = (now_tick - self.elapsed + 1)
+                .min(self.slots.len() as u64);
+            for i in 0..ticks {
+                let idx = ((self.elapsed + i) % self.slots.len() as u64) as usize;
+                self.process(idx, now_tick);
+            }
+        }
+        self.elapsed = now_tick + 1;
+    }
+
+    /// Adds a new entry to the wheel, firing it immediately if its deadline
+    /// has already passed.
+    pub fn insert(&mut self, entry: Arc<Entry>) {
+        let tick = self.deadline_tick(entry.deadline);
+        if tick < self.elapsed {
+            fire(&entry, FIRED);
+            return
+        }
+        let idx = (tick % self.slots.len() as u64) as usize;
+        self.slots[idx].push(entry);
+        self.len += 1;
+    }
+
+    // Fires all entries in the given slot which are due by `now_tick`, and
+    // drops those whose `Delay` has gone away.
+    fn process(&mut self, idx: usize, now_tick: u64) {
+        let mut i = 0;
+        while i < self.slots[idx].len() {
+            let done = {
+                let entry = &self.slots[idx][i];
+                if Arc::strong_count(entry) == 1 {
+                    true
+                } else if self.deadline_tick(entry.deadline) <= now_tick {
+                    fire(entry, FIRED);
+                    true
+                } else {
+                    false
+                }
+            };
+            if done {
+                self.slots[idx].swap_remove(i);
+                self.len -= 1;
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn tick_start(&self, tick: u64) -> Instant {
+        let nanos = self.tick_nanos.saturating_mul(tick);
+        self.start + Duration::new(nanos / 1_000_000_000,
+                                   (nanos % 1_000_000_000) as u32)
+    }
+
+    fn tick_at(&self, at: Instant) -> u64 {
+        nanos_since(self.start, at) / self.tick_nanos
+    }
+
+    // First tick which starts at or after `deadline`, rounding up so entries
+    // never fire early.
+    fn deadline_tick(&self, deadline: Instant) -> u64 {
+        let nanos = nanos_since(self.start, deadline);
+        (nanos + self.tick_nanos - 1) / self.tick_nanos
+    }
+}
+
+impl Drop for Wheel {
+    fn drop(&mut self) {
+        // If we're going away while entries are still pending (the background
+        // thread panicked) then those entries will never fire, so make sure
+        // their `Delay`s find out.
+        for slot in mem::replace(&mut self.slots, Vec::new()) {
+            for entry in slot {
+                fire(&entry, ERROR);
+            }
+        }
+    }
+}
+
+/// Runs the wheel until the last handle to the timer is dropped.
+pub fn run(inner: &Inner, mut wheel: Wheel) {
+    loop {
+        let new = {
+            let mut state = inner.state.lock().unwrap();
+            loop {
+                if state.shutdown {
+                    return
+                }
+                if !state.new.is_empty() {
+                    break mem::replace(&mut state.new, Vec::new())
+                }
+                // Sleep until the earliest pending entry is due, rather than
+                // waking up on every tick in between
+                let next = match wheel.next_deadline() {
+                    Some(next) => next,
+                    None => {
+                        state = inner.cvar.wait(state).unwrap();
+                        continue
+                    }
+                };
+                let now = Instant::now();
+                if now >= next {
+                    break Vec::new()
+                }
+                state = inner.cvar.wait_timeout(state, next - now).unwrap().0;
+            }
+        };
+
+        wheel.advance(Instant::now());
+        for entry in new {
+            wheel.insert(entry);
+        }
+    }
+}
+
+pub fn fire(entry: &Entry, state: usize) {
+    entry.state.store(state, SeqCst);
+    let task = entry.task.lock().unwrap().take();
+    if let Some(task) = task {
+        task.unpark();
+    }
+}
+
+fn nanos_since(start: Instant, at: Instant) -> u64 {
+    if at <= start {
+        return 0
+    }
+    let dur = at - start;
+    dur.as_secs() * 1_000_000_000 + dur.subsec_nanos() as u64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::time::{Duration, Instant};
+
+    use super::Wheel;
+    use super::super::{Entry, PENDING, FIRED};
+
+    fn entry(deadline: Instant) -> Arc<Entry> {
+        Arc::new(Entry {
+            deadline: deadline,
+            state: AtomicUsize::new(PENDING),
+            task: Mutex::new(None),
+        })
+    }
+
+    #[test]
+    fn next_deadline() {
+        let ms = Duration::from_millis;
+        let mut wheel = Wheel::new(ms(10), 4);
+        assert_eq!(wheel.next_deadline(), None);
+
+        // More than a revolution away, in a slot visited before the near one
+        let far = entry(wheel.start + ms(95));
+        let near = entry(wheel.start + ms(25));
+        wheel.insert(far.clone());
+        wheel.insert(near.clone());
+        assert_eq!(wheel.next_deadline(), Some(wheel.start + ms(30)));
+
+        drop(near);
+        assert_eq!(wheel.next_deadline(), Some(wheel.start + ms(100)));
+
+        wheel.advance(wheel.start + ms(100));
+        assert_eq!(wheel.next_deadline(), None);
+        assert_eq!(far.state.load(SeqCst), FIRED);
+    }
+}
@@ -0,0 +1,66 @@
+extern crate futures;
+
+use std::thread;
+use std::time::Duration;
+
+use futures::{Future, Stream, Async};
+use futures::executor;
+use futures::future::{self, AbortError, AbortHandle, Abortable};
+use futures::stream;
+use futures::sync::{mpsc, oneshot};
+
+mod support;
+use support::*;
+
+#[test]
+fn not_aborted() {
+    let (f, _handle) = future::abortable(future::ok::<i32, u32>(1));
+    assert_eq!(f.wait(), Ok(1));
+
+    let (f, _handle) = future::abortable(future::err::<i32, u32>(2));
+    assert_eq!(f.wait(), Err(AbortError::Inner(2)));
+}
+
+#[test]
+fn abort_before_poll() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    let (f, handle) = future::abortable(rx);
+    handle.abort();
+    assert!(f.is_aborted());
+    assert_eq!(f.wait(), Err(AbortError::Aborted));
+
+    // The underlying future has been dropped along with the `Abortable`
+    let mut tx = tx;
+    assert!(future::poll_fn(|| tx.poll_cancel()).wait().is_ok());
+}
+
+#[test]
+fn abort_unparks_task() {
+    let (_tx, rx) = oneshot::channel::<i32>();
+    let (handle, reg) = AbortHandle::new_pair();
+    let mut task = executor::spawn(Abortable::new(rx, reg));
+    assert!(task.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.abort();
+    });
+    assert_eq!(task.wait_future(), Err(AbortError::Aborted));
+    t.join().unwrap();
+}
+
+#[test]
+fn abort_stream() {
+    let (tx, rx) = mpsc::unbounded::<i32>();
+    let (s, handle) = stream::abortable(rx);
+    let mut s = executor::spawn(s);
+
+    tx.clone().send(1).unwrap();
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::NotReady));
+
+    handle.abort();
+    tx.clone().send(2).unwrap();
+    assert_eq!(s.poll_stream(unpark_noop()), Err(AbortError::Aborted));
+    assert_eq!(s.poll_stream(unpark_noop()), Ok(Async::Ready(None)));
+}
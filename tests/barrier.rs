@@ -0,0 +1,82 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor;
+use futures::sync::Barrier;
+
+mod support;
+use support::*;
+
+#[test]
+fn releases_once_all_arrive() {
+    let barrier = Barrier::new(3);
+    let mut a = executor::spawn(barrier.wait());
+    let mut b = executor::spawn(barrier.wait());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    let mut c = executor::spawn(barrier.wait());
+    match c.poll_future(unpark_noop()).unwrap() {
+        Async::Ready(r) => assert!(r.is_leader()),
+        Async::NotReady => panic!("barrier wasn't released"),
+    }
+    match a.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(r) => assert!(!r.is_leader()),
+        Async::NotReady => panic!("waiter wasn't released"),
+    }
+    match b.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(r) => assert!(!r.is_leader()),
+        Async::NotReady => panic!("waiter wasn't released"),
+    }
+}
+
+#[test]
+fn reusable() {
+    let barrier = Barrier::new(2);
+    for _ in 0..3 {
+        let mut a = executor::spawn(barrier.wait());
+        assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+        let mut b = executor::spawn(barrier.wait());
+        assert!(b.poll_future(unpark_noop()).unwrap().is_ready());
+        assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    }
+}
+
+#[test]
+fn zero_and_one() {
+    for &n in [0, 1].iter() {
+        let barrier = Barrier::new(n);
+        assert!(barrier.wait().wait().unwrap().is_leader());
+    }
+}
+
+#[test]
+fn dropped_waiter_withdraws() {
+    let barrier = Barrier::new(2);
+    let mut a = executor::spawn(barrier.wait());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    drop(a);
+
+    let mut b = executor::spawn(barrier.wait());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    let mut c = executor::spawn(barrier.wait());
+    assert!(c.poll_future(unpark_noop()).unwrap().is_ready());
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn one_leader_across_threads() {
+    let barrier = Barrier::new(8);
+    let threads = (0..8).map(|_| {
+        let barrier = barrier.clone();
+        thread::spawn(move || {
+            (0..10).filter(|_| barrier.wait().wait().unwrap().is_leader())
+                   .count()
+        })
+    }).collect::<Vec<_>>();
+    let leaders: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(leaders, 10);
+}
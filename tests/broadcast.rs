@@ -0,0 +1,80 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Async, Future, Stream};
+use futures::executor;
+use futures::sync::broadcast::{self, Lagged};
+
+mod support;
+use support::*;
+
+#[test]
+fn every_receiver_sees_every_message() {
+    let (tx, rx1) = broadcast::channel(4);
+    let rx2 = rx1.clone();
+    let rx3 = tx.subscribe();
+    assert_eq!(tx.receiver_count(), 3);
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    drop(tx);
+
+    assert_eq!(rx1.collect().wait(), Ok(vec![1, 2]));
+    assert_eq!(rx2.collect().wait(), Ok(vec![1, 2]));
+    assert_eq!(rx3.collect().wait(), Ok(vec![1, 2]));
+}
+
+#[test]
+fn subscribe_only_sees_new_messages() {
+    let (tx, rx1) = broadcast::channel(4);
+    tx.send(1).unwrap();
+    let rx2 = tx.subscribe();
+    tx.send(2).unwrap();
+    drop(tx);
+
+    assert_eq!(rx1.collect().wait(), Ok(vec![1, 2]));
+    assert_eq!(rx2.collect().wait(), Ok(vec![2]));
+}
+
+#[test]
+fn slow_receiver_lags() {
+    let (tx, rx) = broadcast::channel(2);
+    let mut rx = rx.wait();
+    for i in 0..5 {
+        tx.send(i).unwrap();
+    }
+    drop(tx);
+
+    assert_eq!(rx.next(), Some(Err(Lagged(3))));
+    assert_eq!(rx.next(), Some(Ok(3)));
+    assert_eq!(rx.next(), Some(Ok(4)));
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn send_fails_without_receivers() {
+    let (tx, rx) = broadcast::channel(1);
+    drop(rx);
+    assert_eq!(tx.send(1).unwrap_err().into_inner(), 1);
+
+    let (tx, rx) = broadcast::channel(1);
+    drop(rx);
+    let msgs = futures::stream::iter(vec![Ok(1)]);
+    assert!(futures::Sink::send_all(tx, msgs).wait().is_err());
+}
+
+#[test]
+fn receiver_is_woken() {
+    let (tx, rx) = broadcast::channel(1);
+    let mut rx = executor::spawn(rx);
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::NotReady));
+
+    let t = thread::spawn(move || {
+        tx.send(1).unwrap();
+    });
+    let mut rx = rx.into_inner().wait();
+    assert_eq!(rx.next(), Some(Ok(1)));
+    assert_eq!(rx.next(), None);
+    t.join().unwrap();
+}
@@ -0,0 +1,97 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Async, Future, Stream};
+use futures::executor;
+use futures::future;
+use futures::stream;
+use futures::sync::{oneshot, CancellationToken};
+
+mod support;
+use support::*;
+
+#[test]
+fn cancelled_future() {
+    let token = CancellationToken::new();
+    assert!(!token.is_cancelled());
+    let mut a = executor::spawn(token.cancelled());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    token.clone().cancel();
+    assert!(token.is_cancelled());
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(token.cancelled().wait().is_ok());
+}
+
+#[test]
+fn parent_cancels_children() {
+    let parent = CancellationToken::new();
+    let child = parent.child_token();
+    let grandchild = child.child_token();
+    let mut a = executor::spawn(grandchild.cancelled());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    parent.cancel();
+    assert!(child.is_cancelled());
+    assert!(grandchild.is_cancelled());
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+
+    // Children of a cancelled token start out cancelled
+    assert!(parent.child_token().is_cancelled());
+}
+
+#[test]
+fn child_leaves_parent_alone() {
+    let parent = CancellationToken::new();
+    let child = parent.child_token();
+    let sibling = parent.child_token();
+    child.cancel();
+    assert!(child.is_cancelled());
+    assert!(!parent.is_cancelled());
+    assert!(!sibling.is_cancelled());
+}
+
+#[test]
+fn cancellable_future() {
+    let token = CancellationToken::new();
+    assert_eq!(token.cancellable(future::ok::<_, ()>(1)).wait(), Ok(Some(1)));
+    assert_eq!(token.cancellable(future::err::<(), _>(2)).wait(), Err(2));
+
+    let (tx, rx) = oneshot::channel::<i32>();
+    let mut a = executor::spawn(token.cancellable(rx));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    token.cancel();
+    assert_eq!(a.poll_future(unpark_panic()).unwrap(), Async::Ready(None));
+
+    // The inner future has been dropped
+    assert!(tx.is_closed());
+}
+
+#[test]
+fn cancellable_stream() {
+    let token = CancellationToken::new();
+    let mut s = token.cancellable(stream::iter(vec![Ok::<_, ()>(1), Ok(2)])).wait();
+    assert_eq!(s.next(), Some(Ok(1)));
+    token.cancel();
+    assert_eq!(s.next(), None);
+
+    let events = token.child_token().cancellable(stream::repeat::<_, ()>(1));
+    assert_eq!(events.collect().wait(), Ok(vec![]));
+}
+
+#[test]
+fn cancel_across_threads() {
+    let token = CancellationToken::new();
+    let threads = (0..4).map(|_| {
+        let child = token.child_token();
+        thread::spawn(move || {
+            child.cancellable(future::empty::<(), ()>()).wait()
+        })
+    }).collect::<Vec<_>>();
+    token.cancel();
+    for t in threads {
+        assert_eq!(t.join().unwrap(), Ok(None));
+    }
+}
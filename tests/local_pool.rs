@@ -0,0 +1,119 @@
+extern crate futures;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::thread;
+
+use futures::{Future, Poll, Async};
+use futures::executor::LocalPool;
+use futures::future::{self, lazy};
+use futures::sync::oneshot;
+
+#[test]
+fn run_until_single_future() {
+    let mut pool = LocalPool::new();
+    assert_eq!(pool.run_until(future::ok::<i32, ()>(1)), Ok(1));
+    assert_eq!(pool.run_until(future::err::<(), i32>(2)), Err(2));
+}
+
+#[test]
+fn run_until_spawned_result() {
+    let mut pool = LocalPool::new();
+    let (tx, rx) = oneshot::channel();
+    let data = Rc::new(RefCell::new(Vec::new()));
+
+    let data2 = data.clone();
+    pool.spawn(lazy(move || {
+        data2.borrow_mut().push(1);
+        tx.complete(data2);
+        Ok(())
+    }));
+
+    let data3 = pool.run_until(rx).unwrap();
+    assert!(Rc::ptr_eq(&data, &data3));
+    assert_eq!(*data.borrow(), [1]);
+}
+
+#[test]
+fn spawn_from_within_pool() {
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let cnt = Rc::new(Cell::new(0));
+
+    let cnt2 = cnt.clone();
+    pool.spawn(lazy(move || {
+        for _ in 0..10 {
+            let cnt = cnt2.clone();
+            spawner.spawn(lazy(move || {
+                cnt.set(cnt.get() + 1);
+                Ok(())
+            }));
+        }
+        Ok(())
+    }));
+
+    pool.run();
+    assert_eq!(cnt.get(), 10);
+}
+
+#[test]
+fn wakeup_from_another_thread() {
+    let mut pool = LocalPool::new();
+    let (tx, rx) = oneshot::channel::<u32>();
+    let (tx2, rx2) = oneshot::channel::<u32>();
+
+    pool.spawn(rx.map(|n| tx2.complete(n + 1)).map_err(|_| ()));
+    let t = thread::spawn(move || tx.complete(1));
+
+    assert_eq!(pool.run_until(rx2), Ok(2));
+    t.join().unwrap();
+}
+
+#[test]
+fn only_unparked_tasks_are_polled() {
+    struct Counted<F> {
+        inner: F,
+        polls: Rc<Cell<usize>>,
+    }
+
+    impl<F: Future> Future for Counted<F> {
+        type Item = F::Item;
+        type Error = F::Error;
+
+        fn poll(&mut self) -> Poll<F::Item, F::Error> {
+            self.polls.set(self.polls.get() + 1);
+            self.inner.poll()
+        }
+    }
+
+    let mut pool = LocalPool::new();
+    let (tx1, rx1) = oneshot::channel::<()>();
+    let (_tx2, rx2) = oneshot::channel::<()>();
+    let polls1 = Rc::new(Cell::new(0));
+    let polls2 = Rc::new(Cell::new(0));
+
+    pool.spawn(Counted { inner: rx1.map_err(|_| ()), polls: polls1.clone() });
+    pool.spawn(Counted { inner: rx2.map_err(|_| ()), polls: polls2.clone() });
+
+    let mut tx1 = Some(tx1);
+    let mut yields = 0;
+    let done = future::poll_fn(move || -> Poll<(), ()> {
+        yields += 1;
+        match yields {
+            1 => {
+                futures::task::park().unpark();
+                Ok(Async::NotReady)
+            }
+            2 => {
+                tx1.take().unwrap().complete(());
+                futures::task::park().unpark();
+                Ok(Async::NotReady)
+            }
+            _ => Ok(Async::Ready(())),
+        }
+    });
+    pool.run_until(done).unwrap();
+
+    assert_eq!(polls1.get(), 2);
+    assert_eq!(polls2.get(), 1);
+}
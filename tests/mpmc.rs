@@ -0,0 +1,128 @@
+#![cfg(feature = "use_std")]
+
+extern crate futures;
+
+use futures::{Future, Stream, Sink, Async, AsyncSink};
+use futures::future::lazy;
+use futures::sync::mpmc;
+
+use std::thread;
+use std::sync::{Arc, Mutex};
+
+fn is_send<T: Send>() {}
+
+#[test]
+fn bounds() {
+    is_send::<mpmc::Sender<i32>>();
+    is_send::<mpmc::Receiver<i32>>();
+}
+
+#[test]
+fn each_message_received_once() {
+    let (tx, rx1) = mpmc::channel::<i32>(16);
+    let rx2 = rx1.clone();
+    let mut rx1 = rx1.wait();
+    let mut rx2 = rx2.wait();
+
+    let tx = tx.send(1).wait().unwrap();
+    let tx = tx.send(2).wait().unwrap();
+    drop(tx);
+
+    assert_eq!(rx2.next(), Some(Ok(1)));
+    assert_eq!(rx1.next(), Some(Ok(2)));
+    assert_eq!(rx1.next(), None);
+    assert_eq!(rx2.next(), None);
+}
+
+#[test]
+fn send_recv_no_buffer() {
+    let (mut tx, rx) = mpmc::channel::<i32>(0);
+    let mut rx2 = rx.clone();
+
+    // Run on a task context
+    lazy(move || {
+        assert!(is_ready(&tx.start_send(1).unwrap()));
+        assert!(!is_ready(&tx.start_send(2).unwrap()));
+
+        // Taking the value out on any receiver unblocks the sender
+        assert_eq!(rx2.poll().unwrap(), Async::Ready(Some(1)));
+        assert!(is_ready(&tx.start_send(2).unwrap()));
+        assert_eq!(rx2.poll().unwrap(), Async::Ready(Some(2)));
+        drop(rx);
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn recv_close_gets_none() {
+    let (mut tx, mut rx) = mpmc::channel::<i32>(10);
+    let mut rx2 = rx.clone();
+
+    // Run on a task context
+    lazy(move || {
+        assert!(is_ready(&tx.start_send(1).unwrap()));
+        rx.close();
+        assert!(tx.start_send(2).is_err());
+
+        assert_eq!(rx2.poll(), Ok(Async::Ready(Some(1))));
+        assert_eq!(rx2.poll(), Ok(Async::Ready(None)));
+        assert_eq!(rx.poll(), Ok(Async::Ready(None)));
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn send_fails_without_receivers() {
+    let (tx, rx) = mpmc::channel::<i32>(10);
+    let rx2 = rx.clone();
+    drop(rx);
+    let tx = tx.send(1).wait().unwrap();
+    drop(rx2);
+    assert_eq!(tx.send(2).wait().unwrap_err().into_inner(), 2);
+}
+
+#[test]
+fn stress_shared_receivers() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 4;
+    let (tx, rx) = mpmc::channel::<usize>(0);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let consumers = (0..NTHREADS).map(|_| {
+        let rx = rx.clone();
+        let seen = seen.clone();
+        thread::spawn(move || {
+            for msg in rx.wait() {
+                seen.lock().unwrap().push(msg.unwrap());
+            }
+        })
+    }).collect::<Vec<_>>();
+    drop(rx);
+
+    let producers = (0..NTHREADS).map(|i| {
+        let mut tx = tx.clone();
+        thread::spawn(move || {
+            for j in 0..AMT {
+                tx = tx.send(i * AMT + j).wait().unwrap();
+            }
+        })
+    }).collect::<Vec<_>>();
+    drop(tx);
+
+    for t in producers.into_iter().chain(consumers) {
+        t.join().unwrap();
+    }
+
+    let mut seen = seen.lock().unwrap();
+    seen.sort();
+    assert_eq!(*seen, (0..AMT * NTHREADS).collect::<Vec<_>>());
+}
+
+fn is_ready<T>(res: &AsyncSink<T>) -> bool {
+    match *res {
+        AsyncSink::Ready => true,
+        _ => false,
+    }
+}
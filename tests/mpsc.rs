@@ -1,10 +1,11 @@
 #![cfg(feature = "use_std")]
 
+#[macro_use]
 extern crate futures;
 
 use futures::{Future, Stream, Sink, Async, AsyncSink};
-use futures::future::lazy;
-use futures::sync::mpsc;
+use futures::future::{self, lazy};
+use futures::sync::mpsc::{self, Overflow, TryRecvError};
 
 use std::time::Duration;
 use std::thread;
@@ -16,6 +17,7 @@ fn is_send<T: Send>() {}
 #[test]
 fn bounds() {
     is_send::<mpsc::Sender<i32>>();
+    is_send::<mpsc::WeakSender<i32>>();
     is_send::<mpsc::Receiver<i32>>();
 }
 
@@ -278,6 +280,363 @@ fn stress_receiver_multi_task_bounded_hard() {
     assert_eq!(AMT, n.load(Ordering::Relaxed));
 }
 
+#[test]
+fn try_send_full_and_disconnected() {
+    let (mut tx, rx) = mpsc::channel::<i32>(0);
+    let mut rx = rx.wait();
+
+    // Outside of a task, the sender's guaranteed slot is used up first
+    assert!(tx.try_send(1).is_ok());
+    let err = tx.try_send(2).unwrap_err();
+    assert!(err.is_full());
+    assert_eq!(err.into_inner(), 2);
+
+    assert_eq!(rx.next().unwrap(), Ok(1));
+    assert!(tx.try_send(2).is_ok());
+    assert_eq!(rx.next().unwrap(), Ok(2));
+
+    drop(rx);
+    let err = tx.try_send(3).unwrap_err();
+    assert!(err.is_disconnected());
+    assert_eq!(err.into_inner(), 3);
+}
+
+#[test]
+fn try_send_then_wait_for_capacity() {
+    let (mut tx, rx) = mpsc::channel::<i32>(0);
+    assert!(tx.try_send(1).is_ok());
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        rx.take(2).collect().wait().unwrap()
+    });
+
+    // Parking through `try_send` still lets a later send wait on capacity
+    tx.send(2).wait().unwrap();
+    assert_eq!(t.join().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn poll_ready() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(0);
+
+    // Run on a task context
+    lazy(move || {
+        assert_eq!(tx.poll_ready().unwrap(), Async::Ready(()));
+        assert!(is_ready(&tx.start_send(1).unwrap()));
+        assert_eq!(tx.poll_ready().unwrap(), Async::NotReady);
+
+        assert_eq!(rx.poll().unwrap(), Async::Ready(Some(1)));
+        assert_eq!(tx.poll_ready().unwrap(), Async::Ready(()));
+
+        drop(rx);
+        assert!(tx.poll_ready().is_err());
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn overflow_drop_newest() {
+    let (mut tx, rx) = mpsc::channel_with_overflow::<i32>(2, Overflow::DropNewest);
+
+    // Run on a task context
+    lazy(move || {
+        for i in 0..4 {
+            assert!(is_ready(&tx.start_send(i).unwrap()));
+        }
+        assert_eq!(tx.dropped_count(), 2);
+        assert_eq!(rx.dropped_count(), 2);
+        drop(tx);
+
+        assert_eq!(rx.collect().wait(), Ok(vec![0, 1]));
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn overflow_drop_oldest() {
+    let (mut tx, rx) = mpsc::channel_with_overflow::<i32>(2, Overflow::DropOldest);
+    let mut rx = rx.wait();
+
+    for i in 0..4 {
+        tx.try_send(i).unwrap();
+    }
+    assert_eq!(rx.next(), Some(Ok(2)));
+
+    tx.try_send(4).unwrap();
+    tx.try_send(5).unwrap();
+    assert_eq!(tx.dropped_count(), 3);
+    drop(tx);
+
+    assert_eq!(rx.next(), Some(Ok(4)));
+    assert_eq!(rx.next(), Some(Ok(5)));
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn overflow_error() {
+    let (mut tx, rx) = mpsc::channel_with_overflow::<i32>(1, Overflow::Error);
+    let mut rx = rx.wait();
+
+    tx.try_send(1).unwrap();
+    // Overflow channels never wait for capacity, so being ready doesn't
+    // mean the next message fits
+    assert_eq!(tx.poll_ready().unwrap(), Async::Ready(()));
+    let err = tx.try_send(2).unwrap_err();
+    assert!(err.is_full());
+    assert_eq!(err.into_inner(), 2);
+    assert_eq!(tx.dropped_count(), 0);
+
+    assert_eq!(rx.next(), Some(Ok(1)));
+    let tx = tx.send(3).wait().unwrap();
+    assert!(tx.send(4).wait().is_err());
+    assert_eq!(rx.next(), Some(Ok(3)));
+}
+
+#[test]
+fn stress_overflow_drop_oldest() {
+    const AMT: usize = 10000;
+    const NTHREADS: usize = 4;
+    let (tx, rx) = mpsc::channel_with_overflow::<usize>(4, Overflow::DropOldest);
+
+    let th = (0..NTHREADS).map(|_| {
+        let mut tx = tx.clone();
+        thread::spawn(move || {
+            for i in 0..AMT {
+                tx = tx.send(i).wait().unwrap();
+            }
+        })
+    }).collect::<Vec<_>>();
+    drop(tx);
+
+    let mut rx = rx;
+    let mut received = 0;
+    for msg in (&mut rx).wait() {
+        assert!(msg.unwrap() < AMT);
+        received += 1;
+    }
+    for t in th {
+        t.join().unwrap();
+    }
+
+    assert_eq!(received + rx.dropped_count(), AMT * NTHREADS);
+}
+
+#[test]
+fn introspection() {
+    let (mut tx, rx) = mpsc::channel::<i32>(2);
+    assert_eq!(tx.capacity(), Some(3));
+    assert_eq!(tx.sender_count(), 1);
+    let tx2 = tx.clone();
+    assert_eq!(rx.capacity(), Some(4));
+    assert_eq!(rx.sender_count(), 2);
+
+    tx.try_send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.len(), 2);
+    assert_eq!(rx.len(), 2);
+    assert!(!rx.is_empty());
+    assert!(!rx.is_closed());
+
+    drop(tx);
+    drop(tx2);
+    assert!(rx.is_closed());
+    assert_eq!(rx.len(), 2);
+
+    let mut rx = rx.wait();
+    assert_eq!(rx.next(), Some(Ok(1)));
+    assert_eq!(rx.next(), Some(Ok(2)));
+    assert_eq!(rx.next(), None);
+
+    let (tx, mut rx) = mpsc::channel_with_overflow::<i32>(2, Overflow::DropNewest);
+    assert_eq!(tx.capacity(), Some(2));
+    rx.close();
+    assert!(tx.is_closed());
+}
+
+#[test]
+fn unbounded_introspection() {
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    assert_eq!(tx.capacity(), None);
+    assert_eq!(rx.sender_count(), 1);
+
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    assert_eq!(tx.len(), 1);
+    assert_eq!(rx.len(), 1);
+
+    drop(rx);
+    assert!(tx.is_closed());
+}
+
+#[test]
+fn weak_sender() {
+    let (tx, rx) = mpsc::channel::<i32>(1);
+    let weak = tx.downgrade();
+    assert_eq!(rx.sender_count(), 1);
+
+    let tx2 = weak.upgrade().unwrap();
+    assert_eq!(rx.sender_count(), 2);
+    drop(tx);
+    tx2.send(1).wait().unwrap();
+
+    // Weak senders don't keep the stream open
+    assert_eq!(rx.collect().wait(), Ok(vec![1]));
+    assert!(weak.upgrade().is_none());
+    assert!(weak.clone().upgrade().is_none());
+}
+
+#[test]
+fn weak_unbounded_sender() {
+    let (tx, rx) = mpsc::unbounded::<i32>();
+    let weak = tx.downgrade();
+
+    let mut tx2 = weak.upgrade().unwrap();
+    drop(tx);
+    mpsc::UnboundedSender::send(&mut tx2, 1).unwrap();
+    drop(tx2);
+
+    assert_eq!(rx.collect().wait(), Ok(vec![1]));
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn try_recv() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(0);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    tx.try_send(1).unwrap();
+    assert!(tx.try_send(2).unwrap_err().is_full());
+    assert_eq!(rx.try_recv(), Ok(1));
+
+    // Receiving unparked the sender
+    tx.try_send(2).unwrap();
+    drop(tx);
+    assert_eq!(rx.try_recv(), Ok(2));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn try_recv_after_close() {
+    let (mut tx, mut rx) = mpsc::unbounded::<i32>();
+    mpsc::UnboundedSender::send(&mut tx, 1).unwrap();
+    rx.close();
+    assert_eq!(rx.try_recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn recv_many() {
+    let (tx, mut rx) = mpsc::channel::<i32>(0);
+    let senders = (0..4).map(|i| {
+        let mut tx = tx.clone();
+        tx.try_send(i).unwrap();
+        tx
+    }).collect::<Vec<_>>();
+    drop(tx);
+    assert_eq!(rx.len(), 4);
+
+    // Run on a task context
+    lazy(move || {
+        let mut buf = Vec::new();
+        assert_eq!(rx.recv_many(&mut buf, 3), Ok(Async::Ready(3)));
+        assert_eq!(buf, vec![0, 1, 2]);
+        assert_eq!(rx.len(), 1);
+
+        assert_eq!(rx.recv_many(&mut buf, 3), Ok(Async::Ready(1)));
+        assert_eq!(buf, vec![0, 1, 2, 3]);
+        assert_eq!(rx.recv_many(&mut buf, 3), Ok(Async::NotReady));
+
+        drop(senders);
+        assert_eq!(rx.recv_many(&mut buf, 3), Ok(Async::Ready(0)));
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn recv_many_unparks_senders() {
+    let (tx, mut rx) = mpsc::channel::<i32>(0);
+
+    let th = (0..4).map(|i| {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let tx = tx.send(i).wait().unwrap();
+            tx.send(i + 10).wait().unwrap();
+        })
+    }).collect::<Vec<_>>();
+    drop(tx);
+
+    let mut buf = Vec::new();
+    future::poll_fn(|| {
+        loop {
+            if try_ready!(rx.recv_many(&mut buf, 16)) == 0 {
+                return Ok::<_, ()>(Async::Ready(()));
+            }
+        }
+    }).wait().unwrap();
+    for t in th {
+        t.join().unwrap();
+    }
+
+    buf.sort();
+    assert_eq!(buf, vec![0, 1, 2, 3, 10, 11, 12, 13]);
+}
+
+#[test]
+fn poll_closed() {
+    let (mut tx, mut rx) = mpsc::channel::<i32>(1);
+
+    // Run on a task context
+    lazy(move || {
+        assert_eq!(tx.poll_closed(), Ok(Async::NotReady));
+        rx.close();
+        assert_eq!(tx.poll_closed(), Ok(Async::Ready(())));
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn closed_future_on_receiver_drop() {
+    let (tx, rx) = mpsc::channel::<i32>(1);
+    let closed = tx.closed();
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+    });
+    assert_eq!(closed.wait(), Ok(()));
+    assert!(tx.is_closed());
+    t.join().unwrap();
+}
+
+#[test]
+fn closed_future_does_not_keep_channel_open() {
+    let (tx, rx) = mpsc::unbounded::<i32>();
+    let closed = tx.closed();
+    drop(tx);
+
+    assert_eq!(rx.collect().wait(), Ok(vec![]));
+    assert_eq!(closed.wait(), Ok(()));
+}
+
+#[test]
+fn unbounded_closed_future() {
+    let (mut tx, rx) = mpsc::unbounded::<i32>();
+    let closed = tx.closed();
+
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(rx);
+    });
+    assert_eq!(closed.wait(), Ok(()));
+    lazy(|| tx.poll_closed()).wait().unwrap();
+    t.join().unwrap();
+}
+
 fn is_ready<T>(res: &AsyncSink<T>) -> bool {
     match *res {
         AsyncSink::Ready => true,
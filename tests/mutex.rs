@@ -0,0 +1,129 @@
+extern crate futures;
+
+use std::sync::{Arc, Mutex as StdMutex};
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor;
+use futures::stream::{self, Stream};
+use futures::sync::Mutex;
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let mutex = Mutex::new(1);
+    let mut lock = mutex.try_lock().unwrap();
+    assert_eq!(*lock, 1);
+    *lock = 2;
+    assert!(mutex.try_lock().is_none());
+    drop(lock);
+    assert_eq!(*mutex.clone().try_lock().unwrap(), 2);
+}
+
+#[test]
+fn fifo_order() {
+    let mutex = Mutex::new(Vec::new());
+    let guard = mutex.try_lock().unwrap();
+
+    let mut waiters = (0..3).map(|i| {
+        executor::spawn(mutex.clone().lock_owned().map(move |mut v| {
+            v.push(i);
+        }))
+    }).collect::<Vec<_>>();
+    for w in waiters.iter_mut().rev() {
+        assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+    }
+    drop(guard);
+
+    // The lock was handed to the first waiter in the queue, so others can't
+    // take it even if they're polled first.
+    assert!(mutex.try_lock().is_none());
+    assert!(waiters[0].poll_future(unpark_noop()).unwrap().is_not_ready());
+    for w in waiters.iter_mut().rev() {
+        assert!(w.poll_future(unpark_noop()).unwrap().is_ready());
+    }
+    assert_eq!(*mutex.try_lock().unwrap(), [2, 1, 0]);
+}
+
+#[test]
+fn unlock_unparks_next_waiter() {
+    struct Flag(StdMutex<bool>);
+
+    impl executor::Unpark for Flag {
+        fn unpark(&self) {
+            *self.0.lock().unwrap() = true;
+        }
+    }
+
+    let mutex = Mutex::new(());
+    let guard = mutex.try_lock().unwrap();
+    let flag = Arc::new(Flag(StdMutex::new(false)));
+    let mut waiter = executor::spawn(mutex.lock());
+    assert!(waiter.poll_future(flag.clone()).unwrap().is_not_ready());
+    assert!(!*flag.0.lock().unwrap());
+    drop(guard);
+    assert!(*flag.0.lock().unwrap());
+    assert!(waiter.poll_future(flag.clone()).unwrap().is_ready());
+}
+
+#[test]
+fn dropped_waiter_leaves_queue() {
+    let mutex = Mutex::new(());
+    let guard = mutex.try_lock().unwrap();
+
+    let mut first = executor::spawn(mutex.lock());
+    let mut second = executor::spawn(mutex.lock());
+    assert!(first.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(second.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(first);
+    drop(guard);
+    match second.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(_) => {}
+        Async::NotReady => panic!("lock wasn't passed on"),
+    };
+}
+
+#[test]
+fn dropped_waiter_passes_on_the_lock() {
+    let mutex = Mutex::new(());
+    let guard = mutex.try_lock().unwrap();
+
+    let mut first = executor::spawn(mutex.lock());
+    let mut second = executor::spawn(mutex.lock());
+    assert!(first.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(second.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // The lock is handed to `first`, which goes away without taking it
+    drop(guard);
+    drop(first);
+    assert!(second.poll_future(unpark_panic()).unwrap().is_ready());
+    drop(second);
+    assert!(mutex.try_lock().is_some());
+}
+
+#[test]
+fn concurrent() {
+    const N: usize = 1000;
+    const THREADS: usize = 4;
+    let mutex = Mutex::new(0);
+
+    let threads = (0..THREADS).map(|_| {
+        let mutex = mutex.clone();
+        thread::spawn(move || {
+            stream::iter((0..N).map(Ok::<_, ()>)).fold(mutex, |mutex, _| {
+                mutex.lock_owned().map(|mut n| {
+                    *n += 1;
+                    n.unlock()
+                })
+            }).wait().unwrap();
+        })
+    }).collect::<Vec<_>>();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert_eq!(*mutex.lock().wait().unwrap(), N * THREADS);
+}
@@ -0,0 +1,111 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::Future;
+use futures::executor;
+use futures::sync::Notify;
+
+mod support;
+use support::*;
+
+#[test]
+fn notify_one_wakes_a_waiter() {
+    let notify = Notify::new();
+    let mut a = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    notify.notify_one();
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn permit_is_stored() {
+    let notify = Notify::new();
+    notify.notify_one();
+    notify.notify_one();
+
+    // Only one permit is kept, no matter how many notifications were sent
+    let mut a = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    let mut b = executor::spawn(notify.notified());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+}
+
+#[test]
+fn notify_one_is_fifo() {
+    let notify = Notify::new();
+    let mut a = executor::spawn(notify.notified());
+    let mut b = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    notify.notify_one();
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    notify.notify_one();
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn repeated_polls_register_once() {
+    let notify = Notify::new();
+    let mut a = executor::spawn(notify.notified());
+    let mut b = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    notify.notify_one();
+    notify.notify_one();
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn notify_all_wakes_everyone() {
+    let notify = Notify::new();
+    let mut a = executor::spawn(notify.notified());
+    let mut b = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    notify.notify_all();
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+
+    // No permit is left behind
+    let mut c = executor::spawn(notify.notified());
+    assert!(c.poll_future(unpark_noop()).unwrap().is_not_ready());
+}
+
+#[test]
+fn dropped_waiter_passes_notification_on() {
+    let notify = Notify::new();
+    let mut a = executor::spawn(notify.notified());
+    let mut b = executor::spawn(notify.notified());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    notify.notify_one();
+    drop(a);
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn ping_pong_across_threads() {
+    let ping = Notify::new();
+    let pong = Notify::new();
+    let (ping2, pong2) = (ping.clone(), pong.clone());
+    let t = thread::spawn(move || {
+        for _ in 0..100 {
+            ping2.notified().wait().unwrap();
+            pong2.notify_one();
+        }
+    });
+    for _ in 0..100 {
+        ping.notify_one();
+        pong.notified().wait().unwrap();
+    }
+    t.join().unwrap();
+}
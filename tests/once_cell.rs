@@ -0,0 +1,142 @@
+extern crate futures;
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor;
+use futures::future;
+use futures::sync::{oneshot, OnceCell};
+
+mod support;
+use support::*;
+
+#[test]
+fn set_and_get() {
+    let cell = OnceCell::new();
+    assert_eq!(cell.get(), None);
+    assert_eq!(cell.set(1), Ok(()));
+    assert_eq!(cell.set(2), Err(2));
+    assert_eq!(cell.get(), Some(&1));
+    assert_eq!(*cell.get_or_init(|| -> Result<i32, ()> { panic!() }).wait().unwrap(), 1);
+}
+
+#[test]
+fn concurrent_callers_share_one_init() {
+    let cell = OnceCell::new();
+    let (tx, rx) = oneshot::channel::<i32>();
+    let calls = AtomicUsize::new(0);
+
+    let mut a = executor::spawn(cell.get_or_init(|| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        rx
+    }));
+    let mut b = executor::spawn(cell.get_or_init(|| {
+        calls.fetch_add(1, Ordering::SeqCst);
+        future::ok::<_, ()>(2)
+    }));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    tx.complete(1);
+    assert_eq!(a.poll_future(unpark_noop()).unwrap(), Async::Ready(&1));
+    assert_eq!(b.poll_future(unpark_panic()).unwrap(), Async::Ready(&1));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn failed_init_is_retried() {
+    let cell = OnceCell::<i32>::new();
+    let (tx, rx) = oneshot::channel::<i32>();
+
+    let mut a = executor::spawn(cell.get_or_init(|| rx));
+    let mut b = executor::spawn(cell.get_or_init(|| future::ok::<_, ()>(2)));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(tx);
+    assert!(a.poll_future(unpark_noop()).is_err());
+    assert_eq!(cell.get(), None);
+    assert_eq!(b.poll_future(unpark_panic()).unwrap(), Async::Ready(&2));
+    assert_eq!(cell.get(), Some(&2));
+}
+
+#[test]
+fn dropped_initializer_is_retried() {
+    let cell = OnceCell::<i32>::new();
+    let (_tx, rx) = oneshot::channel::<i32>();
+
+    let mut a = executor::spawn(cell.get_or_init(|| rx));
+    let mut b = executor::spawn(cell.get_or_init(|| future::ok::<_, ()>(2)));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(a);
+    assert_eq!(b.poll_future(unpark_panic()).unwrap(), Async::Ready(&2));
+}
+
+#[test]
+fn panicking_initializer_is_retried() {
+    let cell = OnceCell::<i32>::new();
+    let cell2 = cell.clone();
+    let res = thread::spawn(move || {
+        drop(cell2.get_or_init(|| -> Result<i32, ()> { panic!("init failed") }).wait());
+    }).join();
+    assert!(res.is_err());
+    assert_eq!(cell.get(), None);
+    assert_eq!(*cell.get_or_init(|| future::ok::<_, ()>(2)).wait().unwrap(), 2);
+}
+
+#[test]
+fn panicking_initializer_wakes_waiters() {
+    let cell = OnceCell::<i32>::new();
+    let (tx, rx) = oneshot::channel::<i32>();
+
+    let mut a = executor::spawn(cell.get_or_init(|| rx.map(|_| -> i32 { panic!("init failed") })));
+    let mut b = executor::spawn(cell.get_or_init(|| future::ok::<_, ()>(2)));
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark.clone()).unwrap().is_not_ready());
+
+    tx.complete(1);
+    let res = panic::catch_unwind(AssertUnwindSafe(|| a.poll_future(unpark_noop()).is_ok()));
+    assert!(res.is_err());
+    drop(a);
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 1);
+    assert_eq!(b.poll_future(unpark_panic()).unwrap(), Async::Ready(&2));
+}
+
+#[test]
+fn init_once_across_threads() {
+    let cell = OnceCell::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let threads = (0..8).map(|i| {
+        let cell = cell.clone();
+        let calls = calls.clone();
+        thread::spawn(move || {
+            let value = cell.get_or_init(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                future::ok::<_, ()>(i)
+            }).wait().unwrap();
+            *value
+        })
+    }).collect::<Vec<_>>();
+    let values = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+    assert!(values.iter().all(|&v| v == values[0]));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn cloned_init_can_outlive_the_handle() {
+    let cell = OnceCell::new();
+    let threads = (0..4).map(|i| {
+        let init = cell.get_or_init_cloned(move || future::ok::<_, ()>(i));
+        thread::spawn(move || init.wait().unwrap())
+    }).collect::<Vec<_>>();
+    let values = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+    assert!(values.iter().all(|&v| v == values[0]));
+    assert_eq!(cell.get(), Some(&values[0]));
+}
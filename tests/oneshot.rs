@@ -89,3 +89,15 @@ fn close_wakes() {
     tx2.send(()).unwrap();
     t.join().unwrap();
 }
+
+#[test]
+fn is_closed() {
+    let (tx, rx) = channel::<u32>();
+    assert!(!tx.is_closed());
+    drop(rx);
+    assert!(tx.is_closed());
+
+    let (tx, mut rx) = channel::<u32>();
+    rx.close();
+    assert!(tx.is_closed());
+}
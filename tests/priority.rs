@@ -0,0 +1,89 @@
+#![cfg(feature = "use_std")]
+
+extern crate futures;
+
+use futures::{Future, Stream, Sink, Async, AsyncSink};
+use futures::future::lazy;
+use futures::sync::priority;
+
+use std::thread;
+
+#[test]
+fn highest_priority_first() {
+    let (tx, rx) = priority::channel::<(u8, &'static str)>(16);
+    let tx = tx.send((0, "bulk 1")).wait().unwrap();
+    let tx = tx.send((0, "bulk 2")).wait().unwrap();
+    let tx = tx.send((2, "shutdown")).wait().unwrap();
+    let tx = tx.send((1, "health")).wait().unwrap();
+    drop(tx);
+
+    assert_eq!(rx.map(|(_, m)| m).collect().wait(),
+               Ok(vec!["shutdown", "health", "bulk 2", "bulk 1"]));
+}
+
+#[test]
+fn equal_priority_is_fifo() {
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Msg(u8);
+
+    let (tx, rx) = priority::channel::<Msg>(16);
+    let tx = tx.send(Msg(1)).wait().unwrap();
+    let tx = tx.send(Msg(1)).wait().unwrap();
+    drop(tx);
+
+    let mut rx = rx.wait();
+    assert_eq!(rx.next(), Some(Ok(Msg(1))));
+    assert_eq!(rx.next(), Some(Ok(Msg(1))));
+    assert_eq!(rx.next(), None);
+}
+
+#[test]
+fn send_recv_no_buffer() {
+    let (mut tx, mut rx) = priority::channel::<i32>(0);
+
+    // Run on a task context
+    lazy(move || {
+        assert!(is_ready(&tx.start_send(1).unwrap()));
+        assert!(!is_ready(&tx.start_send(2).unwrap()));
+
+        assert_eq!(rx.poll().unwrap(), Async::Ready(Some(1)));
+        assert!(is_ready(&tx.start_send(2).unwrap()));
+        assert_eq!(rx.poll().unwrap(), Async::Ready(Some(2)));
+        assert_eq!(rx.poll().unwrap(), Async::NotReady);
+
+        Ok::<(), ()>(())
+    }).wait().unwrap();
+}
+
+#[test]
+fn recv_close_and_drop() {
+    let (tx, mut rx) = priority::channel::<i32>(10);
+    let tx = tx.send(1).wait().unwrap();
+    rx.close();
+    let err = tx.clone().send(2).wait().unwrap_err();
+    assert_eq!(err.into_inner(), 2);
+    assert_eq!(rx.collect().wait(), Ok(vec![1]));
+    assert!(tx.send(3).wait().is_err());
+}
+
+#[test]
+fn send_recv_threads() {
+    let (tx, rx) = priority::channel::<i32>(0);
+
+    let t = thread::spawn(move || {
+        let mut tx = tx;
+        for i in 0..100 {
+            tx = tx.send(i).wait().unwrap();
+        }
+    });
+
+    assert_eq!(rx.fold(0, |n, _| Ok::<_, ()>(n + 1)).wait(), Ok(100));
+    t.join().unwrap();
+}
+
+fn is_ready<T>(res: &AsyncSink<T>) -> bool {
+    match *res {
+        AsyncSink::Ready => true,
+        _ => false,
+    }
+}
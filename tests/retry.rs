@@ -0,0 +1,227 @@
+extern crate futures;
+
+use std::cell::Cell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream, Async};
+use futures::executor;
+use futures::future::{self, RetryPolicy};
+use futures::stream;
+
+mod support;
+use support::*;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn succeeds_after_failures() {
+    let mut attempts = 0;
+    let policy = RetryPolicy::fixed(ms(5));
+    let start = Instant::now();
+    let f = future::retry(policy, || {
+        attempts += 1;
+        if attempts < 4 { Err(attempts) } else { Ok(attempts) }
+    });
+    assert_eq!(f.wait(), Ok(4));
+    assert!(start.elapsed() >= ms(15));
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let mut attempts = 0;
+    let policy = RetryPolicy::fixed(ms(1)).max_attempts(3);
+    let f = future::retry(policy, || {
+        attempts += 1;
+        Err::<(), _>(attempts)
+    });
+    assert_eq!(f.wait(), Err(3));
+}
+
+#[test]
+fn only_retries_retryable_errors() {
+    let mut attempts = 0;
+    let policy = RetryPolicy::fixed(ms(1)).retry_if(|e: &&str| *e == "again");
+    let f = future::retry(policy, || {
+        attempts += 1;
+        if attempts < 3 { Err::<(), _>("again") } else { Err("fatal") }
+    });
+    assert_eq!(f.wait(), Err("fatal"));
+}
+
+#[test]
+fn nothing_runs_until_polled() {
+    let mut ran = false;
+    drop(future::retry(RetryPolicy::<()>::fixed(ms(1)), || {
+        ran = true;
+        Ok::<(), ()>(())
+    }));
+    assert!(!ran);
+}
+
+#[test]
+fn many_immediate_retries_dont_overflow_the_stack() {
+    let mut attempts = 0;
+    let policy = RetryPolicy::fixed(ms(0));
+    let f = future::retry(policy, || {
+        attempts += 1;
+        if attempts < 100_000 { Err(()) } else { Ok(attempts) }
+    });
+    assert_eq!(f.wait(), Ok(100_000));
+}
+
+#[test]
+fn immediate_retries_yield_to_the_executor() {
+    let attempts = Cell::new(0);
+    let f = future::retry(RetryPolicy::fixed(ms(0)), || {
+        attempts.set(attempts.get() + 1);
+        Err::<(), _>(())
+    });
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let mut f = executor::spawn(f);
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(attempts.get(), 1);
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 1);
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(attempts.get(), 2);
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn delays_use_the_given_clock() {
+    let clock = ManualClock::new();
+    let attempts = Cell::new(0);
+    let f = future::Retry::new(RetryPolicy::fixed(ms(100)), || {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() < 3 { Err(attempts.get()) } else { Ok(attempts.get()) }
+    }, clock.clone());
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let mut f = executor::spawn(f);
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(attempts.get(), 1);
+
+    clock.advance(ms(99));
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(attempts.get(), 1);
+
+    clock.advance(ms(1));
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(attempts.get(), 2);
+
+    clock.advance(ms(100));
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::Ready(3)));
+}
+
+#[test]
+fn exponential_delays() {
+    let mut policy = RetryPolicy::exponential(ms(10)).max_delay(ms(50));
+    let delays = (1..6).map(|n| policy.next_delay(n, &()).unwrap())
+                       .collect::<Vec<_>>();
+    assert_eq!(delays, [ms(10), ms(20), ms(40), ms(50), ms(50)]);
+
+    let mut policy = RetryPolicy::exponential(ms(1)).factor(3);
+    assert_eq!(policy.next_delay(3, &()), Some(ms(9)));
+    assert!(policy.next_delay(1_000, &()).is_some());
+
+    let mut policy = RetryPolicy::exponential(ms(1)).max_attempts(2);
+    assert_eq!(policy.next_delay(1, &()), Some(ms(1)));
+    assert_eq!(policy.next_delay(2, &()), None);
+}
+
+#[test]
+fn jittered_delays() {
+    let mut policy = RetryPolicy::exponential(ms(100)).jitter();
+    for n in 1..5 {
+        let max = ms(100) * (1 << (n - 1));
+        let delay = policy.next_delay(n, &()).unwrap();
+        assert!(delay >= max / 2 && delay <= max, "{:?} for {}", delay, n);
+    }
+}
+
+#[test]
+fn stream_resubscribes() {
+    let mut subscriptions = 0;
+    let s = stream::retry(RetryPolicy::fixed(ms(1)), || {
+        subscriptions += 1;
+        let items = if subscriptions < 3 {
+            vec![Ok(subscriptions), Err(())]
+        } else {
+            vec![Ok(subscriptions)]
+        };
+        stream::iter(items)
+    });
+    assert_eq!(s.collect().wait(), Ok(vec![1, 2, 3]));
+}
+
+#[test]
+fn stream_gives_up() {
+    let mut subscriptions = 0;
+    let policy = RetryPolicy::fixed(ms(1)).max_attempts(2);
+    let s = stream::retry(policy, || {
+        subscriptions += 1;
+        stream::iter(vec![Err(subscriptions)])
+    });
+    let mut s = s.wait();
+    assert_eq!(s.next(), Some(Err::<(), _>(2)));
+    assert_eq!(s.next(), None);
+}
+
+#[test]
+fn stream_attempts_reset_after_items() {
+    // The first subscriptions each yield an item before failing, so the limit
+    // on consecutive failures is only reached once one fails straight away.
+    let mut subscriptions = 0;
+    let policy = RetryPolicy::fixed(ms(1)).max_attempts(2);
+    let s = stream::retry(policy, || {
+        subscriptions += 1;
+        if subscriptions < 5 {
+            stream::iter(vec![Ok(subscriptions), Err(subscriptions)])
+        } else {
+            stream::iter(vec![Err(subscriptions)])
+        }
+    });
+    let mut s = s.wait();
+    for i in 1..5 {
+        assert_eq!(s.next(), Some(Ok(i)));
+    }
+    assert_eq!(s.next(), Some(Err(5)));
+    assert_eq!(s.next(), None);
+}
+
+#[test]
+fn stream_immediate_retries_yield_to_the_executor() {
+    let subscriptions = Cell::new(0);
+    let s = stream::retry(RetryPolicy::fixed(ms(0)), || {
+        subscriptions.set(subscriptions.get() + 1);
+        stream::iter(vec![Err::<(), _>(())])
+    });
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let mut s = executor::spawn(s);
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(subscriptions.get(), 1);
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn stream_delays_use_the_given_clock() {
+    let clock = ManualClock::new();
+    let subscriptions = Cell::new(0);
+    let s = stream::Retry::new(RetryPolicy::fixed(ms(100)), || {
+        subscriptions.set(subscriptions.get() + 1);
+        stream::iter(vec![Ok(subscriptions.get()), Err(())])
+    }, clock.clone());
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let mut s = executor::spawn(s);
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::Ready(Some(1))));
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::NotReady));
+
+    clock.advance(ms(99));
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::NotReady));
+    assert_eq!(subscriptions.get(), 1);
+
+    clock.advance(ms(1));
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::Ready(Some(2))));
+}
@@ -0,0 +1,156 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor;
+use futures::stream::{self, Stream};
+use futures::sync::{RwLock, Preference};
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let lock = RwLock::new(1);
+    {
+        let a = lock.try_read().unwrap();
+        let b = lock.try_read().unwrap();
+        assert_eq!(*a + *b, 2);
+        assert!(lock.try_write().is_none());
+    }
+    {
+        let mut w = lock.try_write().unwrap();
+        *w = 2;
+        assert!(lock.try_read().is_none());
+        assert!(lock.try_write().is_none());
+    }
+    assert_eq!(*lock.try_read().unwrap(), 2);
+    assert_eq!(lock.preference(), Preference::Writers);
+}
+
+#[test]
+fn writer_preference_blocks_new_readers() {
+    let lock = RwLock::new(0);
+    let r = lock.try_read().unwrap();
+
+    let mut w = executor::spawn(lock.write());
+    assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // A writer is waiting, so new readers have to wait behind it
+    assert!(lock.try_read().is_none());
+    let mut r2 = executor::spawn(lock.read());
+    assert!(r2.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(r);
+    match w.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(mut g) => {
+            *g = 1;
+            assert!(r2.poll_future(unpark_noop()).unwrap().is_not_ready());
+        }
+        Async::NotReady => panic!("writer wasn't admitted"),
+    };
+    match r2.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(g) => assert_eq!(*g, 1),
+        Async::NotReady => panic!("reader wasn't admitted"),
+    };
+}
+
+#[test]
+fn reader_preference_admits_new_readers() {
+    let lock = RwLock::with_preference(0, Preference::Readers);
+    let r = lock.try_read().unwrap();
+
+    let mut w = executor::spawn(lock.write());
+    assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // Readers can still get in while the writer waits
+    let r2 = lock.try_read().unwrap();
+    drop(r);
+    assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+    drop(r2);
+    assert!(w.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn waiting_readers_admitted_together() {
+    let lock = RwLock::new(0);
+    let w = lock.try_write().unwrap();
+
+    let mut readers = (0..3).map(|_| executor::spawn(lock.read()))
+                            .collect::<Vec<_>>();
+    for r in readers.iter_mut() {
+        assert!(r.poll_future(unpark_noop()).unwrap().is_not_ready());
+    }
+    drop(w);
+    let guards = readers.iter_mut()
+        .map(|r| r.poll_future(unpark_panic()).unwrap())
+        .collect::<Vec<_>>();
+    assert!(guards.iter().all(|g| g.is_ready()));
+    assert!(lock.try_write().is_none());
+}
+
+#[test]
+fn dropped_writer_lets_readers_in() {
+    let lock = RwLock::new(0);
+    let r = lock.try_read().unwrap();
+
+    let mut w = executor::spawn(lock.write());
+    assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+    let mut r2 = executor::spawn(lock.read());
+    assert!(r2.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(w);
+    assert!(r2.poll_future(unpark_panic()).unwrap().is_ready());
+    drop(r);
+}
+
+#[test]
+fn dropped_writer_passes_on_the_lock() {
+    let lock = RwLock::new(0);
+    let r = lock.try_read().unwrap();
+
+    let mut w = executor::spawn(lock.write());
+    let mut r2 = executor::spawn(lock.read());
+    assert!(w.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(r2.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // The lock is handed to the writer, which goes away without taking it
+    drop(r);
+    drop(w);
+    assert!(r2.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn concurrent() {
+    const N: usize = 500;
+    let lock = RwLock::new(0);
+
+    let writers = (0..2).map(|_| {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            stream::iter((0..N).map(Ok::<_, ()>)).fold(lock, |lock, _| {
+                lock.write_owned().map(|mut n| {
+                    *n += 1;
+                    n.unlock()
+                })
+            }).wait().unwrap();
+        })
+    }).collect::<Vec<_>>();
+    let readers = (0..2).map(|_| {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            stream::iter((0..N).map(Ok::<_, ()>)).fold(lock, |lock, _| {
+                lock.read_owned().map(|n| {
+                    assert!(*n <= 2 * N);
+                    n.unlock()
+                })
+            }).wait().unwrap();
+        })
+    }).collect::<Vec<_>>();
+    for t in writers.into_iter().chain(readers) {
+        t.join().unwrap();
+    }
+
+    assert_eq!(*lock.read().wait().unwrap(), 2 * N);
+}
@@ -0,0 +1,133 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use futures::{Async, Future};
+use futures::executor;
+use futures::future;
+use futures::sync::Semaphore;
+
+mod support;
+use support::*;
+
+#[test]
+fn smoke() {
+    let sem = Semaphore::new(3);
+    let a = sem.try_acquire(2).unwrap();
+    assert_eq!(a.permits(), 2);
+    assert_eq!(sem.available_permits(), 1);
+    assert!(sem.try_acquire(2).is_none());
+    let b = sem.clone().try_acquire(1).unwrap();
+    assert_eq!(sem.available_permits(), 0);
+    drop(a);
+    drop(b);
+    assert_eq!(sem.available_permits(), 3);
+}
+
+#[test]
+fn large_request_is_not_starved() {
+    let sem = Semaphore::new(2);
+    let a = sem.try_acquire(1).unwrap();
+
+    let mut big = executor::spawn(sem.acquire(2));
+    assert!(big.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // A permit is still available, but the queued request comes first
+    assert!(sem.try_acquire(1).is_none());
+    let mut small = executor::spawn(sem.acquire(1));
+    assert!(small.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(a);
+    let permit = match big.poll_future(unpark_panic()).unwrap() {
+        Async::Ready(p) => p,
+        Async::NotReady => panic!("permits weren't handed out"),
+    };
+    assert_eq!(permit.permits(), 2);
+    assert!(small.poll_future(unpark_noop()).unwrap().is_not_ready());
+    drop(permit);
+    assert!(small.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn add_permits_wakes_waiters() {
+    let sem = Semaphore::new(0);
+    let mut a = executor::spawn(sem.acquire(1));
+    let mut b = executor::spawn(sem.acquire(2));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    sem.add_permits(2);
+    let permit = a.poll_future(unpark_panic()).unwrap();
+    assert!(permit.is_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+    sem.add_permits(1);
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn dropped_waiter_unblocks_queue() {
+    let sem = Semaphore::new(1);
+    let mut big = executor::spawn(sem.acquire(5));
+    let mut small = executor::spawn(sem.acquire(1));
+    assert!(big.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(small.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    drop(big);
+    assert!(small.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn dropped_waiter_releases_granted_permits() {
+    let sem = Semaphore::new(1);
+    let first = sem.try_acquire(1).unwrap();
+    let mut a = executor::spawn(sem.acquire(1));
+    let mut b = executor::spawn(sem.acquire(1));
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    // The permit is handed to `a`, which goes away without taking it
+    drop(first);
+    drop(a);
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn forget_removes_permits() {
+    let sem = Semaphore::new(2);
+    sem.try_acquire(1).unwrap().forget();
+    assert_eq!(sem.available_permits(), 1);
+}
+
+#[test]
+fn bounds_concurrency() {
+    const LIMIT: usize = 3;
+    let sem = Semaphore::new(LIMIT);
+    let running = Arc::new(AtomicUsize::new(0));
+    let max = Arc::new(AtomicUsize::new(0));
+
+    let threads = (0..8).map(|_| {
+        let sem = sem.clone();
+        let running = running.clone();
+        let max = max.clone();
+        thread::spawn(move || {
+            for _ in 0..50 {
+                sem.acquire(1).and_then(|permit| {
+                    let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(n, Ordering::SeqCst);
+                    thread::yield_now();
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    drop(permit);
+                    future::ok(())
+                }).wait().unwrap();
+            }
+        })
+    }).collect::<Vec<_>>();
+    for t in threads {
+        t.join().unwrap();
+    }
+
+    assert!(max.load(Ordering::SeqCst) <= LIMIT);
+    assert_eq!(sem.available_permits(), LIMIT);
+}
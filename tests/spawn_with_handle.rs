@@ -0,0 +1,53 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::thread;
+
+use futures::Future;
+use futures::executor::{self, Executor, Run, SpawnError};
+use futures::future;
+use futures::sync::oneshot;
+
+struct ThreadPerRun;
+
+impl Executor for ThreadPerRun {
+    fn execute(&self, r: Run) {
+        thread::spawn(move || r.run());
+    }
+}
+
+struct DropRuns;
+
+impl Executor for DropRuns {
+    fn execute(&self, r: Run) {
+        drop(r);
+    }
+}
+
+#[test]
+fn item_and_error() {
+    let ok = executor::spawn_with_handle(future::ok::<u32, u32>(1),
+                                         Arc::new(ThreadPerRun));
+    assert_eq!(ok.wait(), Ok(1));
+
+    let err = executor::spawn_with_handle(future::err::<u32, u32>(2),
+                                          Arc::new(ThreadPerRun));
+    assert_eq!(err.wait(), Err(SpawnError::Inner(2)));
+}
+
+#[test]
+fn resolves_after_unpark() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let handle = executor::spawn_with_handle(rx.map(|n| n * 2),
+                                             Arc::new(ThreadPerRun));
+    thread::spawn(move || tx.complete(21));
+    assert_eq!(handle.wait(), Ok(42));
+}
+
+#[test]
+fn canceled_when_run_dropped() {
+    let handle = executor::spawn_with_handle(future::ok::<u32, u32>(1),
+                                             Arc::new(DropRuns));
+    assert_eq!(handle.wait(), Err(SpawnError::Canceled));
+}
+
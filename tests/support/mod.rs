@@ -1,14 +1,17 @@
 #![allow(dead_code)]
 
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use futures::{Future, IntoFuture, Async, Poll};
 use futures::future::FutureResult;
 use futures::stream::Stream;
 use futures::executor::{self, Unpark};
-use futures::task;
+use futures::task::{self, Task};
+use futures::timer::{Clock, TimerError};
 
 pub fn f_ok(a: i32) -> FutureResult<i32, u32> { Ok(a).into_future() }
 pub fn f_err(a: u32) -> FutureResult<i32, u32> { Err(a).into_future() }
@@ -128,3 +131,76 @@ pub fn delay_future<F>(f: F) -> DelayFuture<F::Future>
     DelayFuture(f.into_future(), false)
 }
 
+// A clock which only moves forward when told to.
+#[derive(Clone)]
+pub struct ManualClock {
+    inner: Arc<Mutex<ClockState>>,
+}
+
+struct ClockState {
+    now: Instant,
+    waiters: Vec<Task>,
+}
+
+pub struct ManualDelay {
+    clock: ManualClock,
+    at: Instant,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock {
+            inner: Arc::new(Mutex::new(ClockState {
+                now: Instant::now(),
+                waiters: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn advance(&self, dur: Duration) {
+        let waiters = {
+            let mut state = self.inner.lock().unwrap();
+            state.now += dur;
+            state.waiters.drain(..).collect::<Vec<_>>()
+        };
+        for task in waiters {
+            task.unpark();
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    type Delay = ManualDelay;
+
+    fn now(&self) -> Instant {
+        self.inner.lock().unwrap().now
+    }
+
+    fn delay_until(&self, at: Instant) -> ManualDelay {
+        ManualDelay { clock: self.clone(), at: at }
+    }
+}
+
+impl Future for ManualDelay {
+    type Item = ();
+    type Error = TimerError;
+
+    fn poll(&mut self) -> Poll<(), TimerError> {
+        let mut state = self.clock.inner.lock().unwrap();
+        if state.now >= self.at {
+            Ok(Async::Ready(()))
+        } else {
+            state.waiters.push(task::park());
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+// Counts how many times it's been unparked.
+pub struct Count(pub AtomicUsize);
+
+impl Unpark for Count {
+    fn unpark(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,90 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::thread;
+
+use futures::Future;
+use futures::executor::{Executor, Run, SpawnError, TaskGroup, FailurePolicy};
+use futures::future;
+use futures::sync::oneshot;
+
+struct ThreadPerRun;
+
+impl Executor for ThreadPerRun {
+    fn execute(&self, r: Run) {
+        thread::spawn(move || r.run());
+    }
+}
+
+struct DropRuns;
+
+impl Executor for DropRuns {
+    fn execute(&self, r: Run) {
+        drop(r);
+    }
+}
+
+#[test]
+fn resolves_in_spawn_order() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut group = TaskGroup::new(Arc::new(ThreadPerRun));
+    group.spawn(rx.map_err(|_| ()));
+    group.spawn(future::ok(2));
+    thread::spawn(move || tx.complete(1));
+    assert_eq!(group.wait(), Ok(vec![1, 2]));
+}
+
+#[test]
+fn empty_group() {
+    let group = TaskGroup::<(), ()>::new(Arc::new(ThreadPerRun));
+    assert_eq!(group.wait(), Ok(vec![]));
+}
+
+#[test]
+fn failure_cancels_remaining() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut group = TaskGroup::new(Arc::new(ThreadPerRun));
+    group.spawn(rx.map_err(|_| 0));
+    group.spawn(future::err(3));
+    assert_eq!(group.wait(), Err(SpawnError::Inner(3)));
+
+    // The pending child is aborted, dropping its receiver
+    wait_until_closed(tx);
+}
+
+#[test]
+fn failure_waits_for_all() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let (done_tx, done_rx) = oneshot::channel();
+    let mut group = TaskGroup::with_policy(Arc::new(ThreadPerRun),
+                                           FailurePolicy::WaitForAll);
+    group.spawn(rx.map_err(|_| 0).map(move |n| {
+        done_tx.complete(n);
+        n
+    }));
+    group.spawn(future::err(3));
+    thread::spawn(move || tx.complete(1));
+    assert_eq!(group.wait(), Err(SpawnError::Inner(3)));
+    assert_eq!(done_rx.wait(), Ok(1));
+}
+
+#[test]
+fn drop_aborts_children() {
+    let (tx, rx) = oneshot::channel::<u32>();
+    let mut group = TaskGroup::new(Arc::new(ThreadPerRun));
+    group.spawn(rx.map_err(|_| ()));
+    drop(group);
+    wait_until_closed(tx);
+}
+
+#[test]
+fn canceled_by_executor() {
+    let mut group = TaskGroup::new(Arc::new(DropRuns));
+    group.spawn(future::ok::<u32, u32>(1));
+    assert_eq!(group.wait(), Err(SpawnError::Canceled));
+}
+
+fn wait_until_closed<T>(mut tx: oneshot::Sender<T>) {
+    future::poll_fn(|| tx.poll_cancel()).wait().unwrap();
+    assert!(tx.is_closed());
+}
@@ -0,0 +1,98 @@
+extern crate futures;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream, Sink, Async};
+use futures::executor;
+use futures::future;
+use futures::stream;
+use futures::sync::{mpsc, oneshot};
+use futures::timer::TimeoutError;
+
+mod support;
+use support::*;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn future_elapses() {
+    let clock = ManualClock::new();
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let (_tx, rx) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(future::Timeout::new(rx, ms(100), clock.clone()));
+
+    assert!(f.poll_future(unpark.clone()).unwrap().is_not_ready());
+    clock.advance(ms(99));
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 1);
+    assert!(f.poll_future(unpark.clone()).unwrap().is_not_ready());
+    clock.advance(ms(1));
+    assert_eq!(unpark.0.load(Ordering::SeqCst), 2);
+    assert_eq!(f.poll_future(unpark.clone()), Err(TimeoutError::Elapsed));
+}
+
+#[test]
+fn future_completes_in_time() {
+    let clock = ManualClock::new();
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let (tx, rx) = oneshot::channel::<i32>();
+    let mut f = executor::spawn(future::Timeout::new(rx, ms(100), clock.clone()));
+
+    assert!(f.poll_future(unpark.clone()).unwrap().is_not_ready());
+    tx.complete(3);
+    clock.advance(ms(200));
+    assert_eq!(f.poll_future(unpark.clone()), Ok(Async::Ready(3)));
+
+    let f = future::Timeout::new(future::err::<(), _>(4), ms(100), clock);
+    assert_eq!(f.wait(), Err(TimeoutError::Inner(4)));
+}
+
+#[test]
+fn future_timeout_with_default_timer() {
+    let start = Instant::now();
+    let f = future::empty::<(), ()>().timeout(ms(20));
+    assert_eq!(f.wait(), Err(TimeoutError::Elapsed));
+    assert!(start.elapsed() >= ms(20));
+
+    assert_eq!(future::ok::<_, ()>(1).timeout(ms(1_000)).wait(), Ok(1));
+}
+
+#[test]
+fn stream_deadline_is_per_item() {
+    let clock = ManualClock::new();
+    let unpark = Arc::new(Count(AtomicUsize::new(0)));
+    let (tx, rx) = mpsc::channel::<i32>(1);
+    let mut s = executor::spawn(stream::Timeout::new(rx, ms(100), clock.clone()));
+
+    assert!(s.poll_stream(unpark.clone()).unwrap().is_not_ready());
+    clock.advance(ms(60));
+    let tx = tx.send(1).wait().unwrap();
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::Ready(Some(1))));
+
+    // The deadline restarted when the item was yielded
+    clock.advance(ms(60));
+    assert!(s.poll_stream(unpark.clone()).unwrap().is_not_ready());
+    clock.advance(ms(40));
+    assert_eq!(s.poll_stream(unpark.clone()), Err(TimeoutError::Elapsed));
+
+    // The stream keeps going after a timeout
+    assert!(s.poll_stream(unpark.clone()).unwrap().is_not_ready());
+    drop(tx.send(2).wait().unwrap());
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::Ready(Some(2))));
+    assert_eq!(s.poll_stream(unpark.clone()), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn stream_timeout_with_default_timer() {
+    let (tx, rx) = mpsc::channel::<i32>(1);
+    let mut items = rx.timeout(ms(20)).wait();
+    assert_eq!(items.next(), Some(Err(TimeoutError::Elapsed)));
+
+    let tx = tx.send(1).wait().unwrap();
+    assert_eq!(items.next(), Some(Ok(1)));
+    drop(tx);
+    assert_eq!(items.next(), None);
+}
@@ -0,0 +1,111 @@
+extern crate futures;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::{Future, Stream};
+use futures::timer::{self, Builder, Timer};
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn delay_resolves_after_deadline() {
+    let start = Instant::now();
+    timer::delay(ms(50)).wait().unwrap();
+    assert!(start.elapsed() >= ms(50));
+}
+
+#[test]
+fn delay_in_the_past() {
+    let timer = Timer::new();
+    let d = timer.delay_until(Instant::now() - ms(10));
+    assert!(d.is_elapsed());
+    d.wait().unwrap();
+}
+
+#[test]
+fn many_delays_fire_in_order() {
+    let timer = Builder::new().num_slots(8).create();
+    let (tx, rx) = mpsc::channel();
+
+    let threads = (0..5).rev().map(|i| {
+        let d = timer.delay(ms(20 * i as u64 + 10));
+        let tx = tx.clone();
+        thread::spawn(move || {
+            d.wait().unwrap();
+            tx.send(i).unwrap();
+        })
+    }).collect::<Vec<_>>();
+    drop(tx);
+
+    assert_eq!(rx.iter().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+#[test]
+fn delay_longer_than_a_revolution() {
+    let timer = Builder::new()
+        .tick_duration(ms(1))
+        .num_slots(4)
+        .create();
+    let start = Instant::now();
+    timer.delay(ms(30)).wait().unwrap();
+    assert!(start.elapsed() >= ms(30));
+}
+
+#[test]
+fn dropped_delays_are_canceled() {
+    let timer = Timer::new();
+    let start = Instant::now();
+    let long = timer.delay(ms(10_000));
+    let short = timer.delay(ms(20));
+    let (_, other) = long.select(short).wait().ok().unwrap();
+    drop(other);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= ms(20));
+    assert!(elapsed < ms(10_000));
+
+    timer.delay(ms(10)).wait().unwrap();
+}
+
+#[test]
+fn reset_delay() {
+    let timer = Timer::new();
+    let mut d = timer.delay(ms(10_000));
+    let start = Instant::now();
+    d.reset(start + ms(20));
+    d.wait().unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= ms(20));
+    assert!(elapsed < ms(10_000));
+}
+
+#[test]
+fn interval_does_not_drift() {
+    let timer = Timer::new();
+    let start = Instant::now();
+    let interval = timer.interval(ms(20));
+    assert_eq!(interval.period(), ms(20));
+
+    let mut ticks = interval.take(5).wait();
+    ticks.next().unwrap().unwrap();
+    // Falling behind shouldn't push back later ticks
+    thread::sleep(ms(50));
+    for tick in ticks {
+        tick.unwrap();
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= ms(100));
+    assert!(elapsed < ms(1_000));
+}
+
+#[test]
+fn timer_outlives_handle() {
+    let d = Timer::new().delay(ms(20));
+    d.wait().unwrap();
+}
@@ -0,0 +1,73 @@
+extern crate futures;
+
+use std::thread;
+
+use futures::Future;
+use futures::executor;
+use futures::sync::WaitGroup;
+
+mod support;
+use support::*;
+
+#[test]
+fn ready_at_zero() {
+    let wg = WaitGroup::new();
+    assert_eq!(wg.count(), 0);
+    assert!(executor::spawn(wg.wait()).poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn waits_for_count_to_reach_zero() {
+    let wg = WaitGroup::with_count(1);
+    wg.add(1);
+    let mut a = executor::spawn(wg.wait());
+    let mut b = executor::spawn(wg.wait());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+
+    wg.done();
+    assert_eq!(wg.count(), 1);
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    wg.done();
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+    assert!(b.poll_future(unpark_panic()).unwrap().is_ready());
+}
+
+#[test]
+fn released_even_if_raised_again() {
+    let wg = WaitGroup::with_count(1);
+    let mut a = executor::spawn(wg.wait());
+    assert!(a.poll_future(unpark_noop()).unwrap().is_not_ready());
+    wg.done();
+    wg.add(1);
+    assert!(a.poll_future(unpark_panic()).unwrap().is_ready());
+
+    let mut b = executor::spawn(wg.wait());
+    assert!(b.poll_future(unpark_noop()).unwrap().is_not_ready());
+}
+
+#[test]
+#[should_panic]
+fn done_below_zero() {
+    WaitGroup::new().done();
+}
+
+#[test]
+fn work_added_while_running() {
+    let wg = WaitGroup::with_count(1);
+    let wg2 = wg.clone();
+    let t = thread::spawn(move || {
+        let threads = (0..4).map(|_| {
+            wg2.add(1);
+            let wg = wg2.clone();
+            thread::spawn(move || wg.done())
+        }).collect::<Vec<_>>();
+        wg2.done();
+        threads
+    });
+    wg.wait().wait().unwrap();
+    assert_eq!(wg.count(), 0);
+    for t in t.join().unwrap() {
+        t.join().unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+extern crate futures;
+
+use std::thread;
+use std::time::Duration;
+
+use futures::{Async, Future, Stream};
+use futures::executor;
+use futures::sync::watch;
+
+mod support;
+use support::*;
+
+#[test]
+fn only_latest_value_is_seen() {
+    let (tx, rx) = watch::channel(0);
+    let mut rx = executor::spawn(rx);
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::NotReady));
+
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(*tx.borrow(), 2);
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::Ready(Some(2))));
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::NotReady));
+
+    drop(tx);
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::Ready(None)));
+}
+
+#[test]
+fn borrow_gives_current_value() {
+    let (tx, rx) = watch::channel("a");
+    assert_eq!(*rx.borrow(), "a");
+    tx.send("b").unwrap();
+    assert_eq!(*rx.borrow(), "b");
+
+    // Borrowing doesn't mark the value as seen
+    drop(tx);
+    assert_eq!(rx.collect().wait(), Ok(vec!["b"]));
+}
+
+#[test]
+fn clones_track_versions_independently() {
+    let (tx, rx1) = watch::channel(0);
+    tx.send(1).unwrap();
+    let mut rx2 = rx1.clone().wait();
+    assert_eq!(rx2.next(), Some(Ok(1)));
+
+    tx.send(2).unwrap();
+    drop(tx);
+    assert_eq!(rx2.next(), Some(Ok(2)));
+    assert_eq!(rx2.next(), None);
+    assert_eq!(rx1.collect().wait(), Ok(vec![2]));
+}
+
+#[test]
+fn send_fails_without_receivers() {
+    let (tx, rx) = watch::channel(0);
+    drop(rx);
+    assert_eq!(tx.send(1).unwrap_err().into_inner(), 1);
+    assert_eq!(*tx.borrow(), 0);
+}
+
+#[test]
+fn receiver_is_woken() {
+    let (tx, rx) = watch::channel(0);
+    let mut rx = executor::spawn(rx);
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::NotReady));
+
+    let t = thread::spawn(move || {
+        tx.send(1).unwrap();
+    });
+    let mut rx = rx.into_inner().wait();
+    assert_eq!(rx.next(), Some(Ok(1)));
+    assert_eq!(rx.next(), None);
+    t.join().unwrap();
+}
+
+#[test]
+fn borrow_held_across_send() {
+    let (tx, rx) = watch::channel(0);
+    let other = rx.clone();
+    let mut rx = executor::spawn(rx);
+    let value = other.borrow();
+
+    // The send blocks until the borrow is released, but mustn't keep the
+    // receiver from being polled in the meantime.
+    let t = thread::spawn(move || {
+        tx.send(1).unwrap();
+        tx
+    });
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::NotReady));
+    assert_eq!(*value, 0);
+    drop(value);
+
+    let tx = t.join().unwrap();
+    assert_eq!(rx.poll_stream(unpark_noop()), Ok(Async::Ready(Some(1))));
+    drop(tx);
+}